@@ -20,10 +20,28 @@ pub fn read_mem<T>(h_proc: HANDLE, address: usize) -> Result<T, WIN32_ERROR> {
             None,
         );
     }
-    return match ok.as_bool() {
+    match ok.as_bool() {
         true => Ok(result),
         false => Err(unsafe { GetLastError() }),
+    }
+}
+
+/// Reads a whole range at once, e.g. a module to rebuild with `PeBuilder::from_image`
+pub fn read_bytes(h_proc: HANDLE, address: usize, size: usize) -> Result<Vec<u8>, WIN32_ERROR> {
+    let mut buffer = vec![0u8; size];
+    let ok = unsafe {
+        ReadProcessMemory(
+            h_proc,
+            address as *mut c_void,
+            buffer.as_mut_ptr() as *mut c_void,
+            size,
+            None,
+        )
     };
+    match ok.as_bool() {
+        true => Ok(buffer),
+        false => Err(unsafe { GetLastError() }),
+    }
 }
 
 macro_rules! gen_multilevel_ptr {
@@ -35,11 +53,11 @@ macro_rules! gen_multilevel_ptr {
                     starting_address: $_type,
                     offsets: Vec<$_type>,
                 ) -> Result<$_type, WIN32_ERROR> {
-                    let mut addr = starting_address as $_type;
+                    let mut addr = starting_address;
 
                     for offset in offsets {
-                            addr = [<read_mem_$_type>](h_proc, addr as usize)?;
-                        addr = addr + offset;
+                        addr = [<read_mem_$_type>](h_proc, addr as usize)?;
+                        addr += offset;
                     }
                     Ok(addr)
                 }
@@ -85,10 +103,10 @@ pub fn write_mem<T>(h_proc: HANDLE, address: *mut c_void, value: T) -> Result<()
             None,
         );
     }
-    return match ok.as_bool() {
+    match ok.as_bool() {
         true => Ok(()),
         false => Err(unsafe { GetLastError() }),
-    };
+    }
 }
 
 macro_rules! gen_patch {
@@ -129,11 +147,13 @@ macro_rules! gen_patch {
 gen_patch!(u64, u32);
 
 pub fn nop_32(dest: u32, size: usize, h_proc: HANDLE) {
-    let nop_array: *mut u8 = vec![0x90; size].as_mut_ptr();
-    patch_u32(dest, nop_array, size, h_proc);
+    //  the buffer has to outlive the write, a pointer to a temporary dangles
+    let nop_array = vec![0x90u8; size];
+    patch_u32(dest, nop_array.as_ptr(), size, h_proc);
 }
 
 pub fn nop_64(dest: u64, size: usize, h_proc: HANDLE) {
-    let nop_array: *mut u8 = vec![0x90; size].as_mut_ptr();
-    patch_u64(dest, nop_array, size, h_proc);
+    //  the buffer has to outlive the write, a pointer to a temporary dangles
+    let nop_array = vec![0x90u8; size];
+    patch_u64(dest, nop_array.as_ptr(), size, h_proc);
 }
@@ -1,9 +1,13 @@
+#[cfg(all(windows, feature = "process"))]
 pub mod external;
 pub mod internal;
 pub mod prelude;
+#[cfg(all(windows, feature = "process"))]
 pub mod process;
 pub mod util;
 pub mod vec;
 pub use vec::Vec3;
+#[cfg(feature = "pe")]
 pub mod pe;
+#[cfg(all(windows, feature = "process"))]
 pub use windows;
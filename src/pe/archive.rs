@@ -0,0 +1,567 @@
+#![allow(non_camel_case_types)]
+
+use super::{
+    builder::ImportSymbol,
+    coff::CoffObject,
+    cursor::{cstr_at, slice_at, Cursor},
+    file_header::Machine,
+    PeError,
+};
+
+const ARCHIVE_MAGIC: &[u8] = b"!<arch>\n";
+const MEMBER_HEADER_SIZE: usize = 60;
+const IMPORT_OBJECT_SIGNATURE: [u8; 4] = [0, 0, 0xff, 0xff];
+
+/// A .lib, MSVC writes both linker members and null terminated long names,
+/// MinGW and llvm only the first linker member and "/\n" terminated long names
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#archive-library-file-format
+#[derive(Debug, Clone)]
+pub struct Archive {
+    /// Every public symbol with the member that defines it, in member order
+    pub first_linker_member: Vec<ArchiveSymbol>,
+    /// The same symbols sorted by name, only in MSVC libraries
+    pub second_linker_member: Option<Vec<ArchiveSymbol>>,
+    pub members: Vec<ArchiveMember>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ArchiveSymbol {
+    pub name: String,
+    /// File offset of the header of the member defining the symbol
+    pub member_offset: u32,
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#archive-member-headers
+#[derive(Debug, Clone)]
+pub struct ArchiveMember {
+    /// The name with long names resolved and the "/" terminator removed
+    pub name: String,
+    /// File offset of the member header, what the linker members point to
+    pub header_offset: u32,
+    pub date: u64,
+    pub data: Vec<u8>,
+}
+
+impl Archive {
+    pub fn from_file(path: impl Into<String>) -> Result<Archive, PeError> {
+        let file = std::fs::read(path.into())?;
+        Archive::try_from(file.as_slice())
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Self, PeError> {
+        if !bytes.starts_with(ARCHIVE_MAGIC) {
+            return Err(PeError::ParseError(
+                "The file doesn't start with the archive signature".to_string(),
+            ));
+        }
+        let mut first_linker_member = None;
+        let mut second_linker_member = None;
+        let mut longnames: &[u8] = &[];
+        let mut members = vec![];
+
+        let mut offset = ARCHIVE_MAGIC.len();
+        while offset < bytes.len() {
+            let header = parse_member_header(bytes, offset)?;
+            let data = slice_at(bytes, offset + MEMBER_HEADER_SIZE, header.size)?;
+            let name = match header.name {
+                "/" if first_linker_member.is_none() => {
+                    first_linker_member = Some(parse_first_linker_member(data)?);
+                    None
+                }
+                "/" => {
+                    second_linker_member = Some(parse_second_linker_member(data)?);
+                    None
+                }
+                "//" => {
+                    longnames = data;
+                    None
+                }
+                name => match name.strip_prefix('/') {
+                    Some(index) if index.bytes().all(|c| c.is_ascii_digit()) => {
+                        Some(long_name(longnames, index)?)
+                    }
+                    //  other special members, e.g. /SYM64/ or /<ECSYMBOLS>/
+                    Some(_) => None,
+                    None => Some(name.trim_end_matches('/').to_string()),
+                },
+            };
+            if let Some(name) = name {
+                members.push(ArchiveMember {
+                    name,
+                    header_offset: offset as u32,
+                    date: header.date,
+                    data: data.to_vec(),
+                });
+            }
+            //  members start on an even offset
+            offset += MEMBER_HEADER_SIZE + header.size.next_multiple_of(2);
+        }
+        Ok(Archive {
+            first_linker_member: first_linker_member.unwrap_or_default(),
+            second_linker_member,
+            members,
+        })
+    }
+
+    /// The member that defines `symbol`
+    pub fn member_for_symbol(&self, symbol: &str) -> Option<&ArchiveMember> {
+        let symbols = self
+            .second_linker_member
+            .as_ref()
+            .unwrap_or(&self.first_linker_member);
+        let offset = symbols.iter().find(|s| s.name == symbol)?.member_offset;
+        self.members
+            .iter()
+            .find(|member| member.header_offset == offset)
+    }
+
+    pub fn member(&self, name: &str) -> Option<&ArchiveMember> {
+        self.members.iter().find(|member| member.name == name)
+    }
+
+    /// Every short import in an import library. Libraries built by binutils' dlltool
+    /// use a full object per import instead, those are skipped
+    pub fn imports(&self) -> Result<Vec<ImportObjectHeader>, PeError> {
+        self.members
+            .iter()
+            .filter(|member| member.is_import_object())
+            .map(|member| member.import_object())
+            .collect()
+    }
+}
+
+impl TryFrom<&[u8]> for Archive {
+    type Error = PeError;
+    fn try_from(data: &[u8]) -> Result<Self, PeError> {
+        Self::parse(data)
+    }
+}
+
+impl ArchiveMember {
+    pub fn is_import_object(&self) -> bool {
+        self.data.starts_with(&IMPORT_OBJECT_SIGNATURE)
+    }
+
+    pub fn object(&self) -> Result<CoffObject, PeError> {
+        CoffObject::try_from(self.data.as_slice())
+    }
+
+    /// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#import-library-format
+    pub fn import_object(&self) -> Result<ImportObjectHeader, PeError> {
+        let mut cursor = Cursor::new(&self.data);
+        if cursor.read_slice(4)? != IMPORT_OBJECT_SIGNATURE {
+            return Err(PeError::ParseError(format!(
+                "The member {} isn't an import object",
+                self.name
+            )));
+        }
+        let version = cursor.read_u16()?;
+        let machine = Machine::try_from(cursor.read_u16()?)?;
+        let time_date_stamp = cursor.read_u32()?;
+        let size_of_data = cursor.read_u32()?;
+        let ordinal_or_hint = cursor.read_u16()?;
+        let flags = cursor.read_u16()?;
+        let strings = cursor.read_slice(size_of_data as usize)?;
+        //  the null terminated symbol name, DLL name and, for EXPORTAS, export name
+        let symbol_name = cstr_at(strings, 0)?;
+        let dll = cstr_at(strings, symbol_name.len() + 1)?;
+        let name_type = ImportNameType::try_from((flags >> 2) & 0b111)?;
+        let export_name = match name_type {
+            ImportNameType::IMPORT_OBJECT_NAME_EXPORTAS => Some(
+                String::from_utf8_lossy(cstr_at(strings, symbol_name.len() + dll.len() + 2)?)
+                    .to_string(),
+            ),
+            _ => None,
+        };
+        Ok(ImportObjectHeader {
+            version,
+            machine,
+            time_date_stamp,
+            size_of_data,
+            ordinal_or_hint,
+            r#type: ImportObjectType::try_from(flags & 0b11)?,
+            name_type,
+            symbol_name: String::from_utf8_lossy(symbol_name).to_string(),
+            dll: String::from_utf8_lossy(dll).to_string(),
+            export_name,
+        })
+    }
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#import-header
+#[derive(Debug, Clone)]
+pub struct ImportObjectHeader {
+    pub version: u16,
+    pub machine: Machine,
+    pub time_date_stamp: u32,
+    pub size_of_data: u32,
+    /// The ordinal for IMPORT_OBJECT_ORDINAL, the export table hint otherwise
+    pub ordinal_or_hint: u16,
+    pub r#type: ImportObjectType,
+    pub name_type: ImportNameType,
+    /// The public symbol the linker resolves, decorated
+    pub symbol_name: String,
+    pub dll: String,
+    /// Only for IMPORT_OBJECT_NAME_EXPORTAS
+    pub export_name: Option<String>,
+}
+
+impl ImportObjectHeader {
+    /// What the loader looks up in the DLL, derived from the symbol name and the name type
+    /// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#import-name-type
+    pub fn import_symbol(&self) -> ImportSymbol {
+        use ImportNameType::*;
+        let without_prefix = || self.symbol_name.trim_start_matches(['?', '@', '_']);
+        match self.name_type {
+            IMPORT_OBJECT_ORDINAL => ImportSymbol::Ordinal(self.ordinal_or_hint),
+            IMPORT_OBJECT_NAME => ImportSymbol::Name(self.symbol_name.clone()),
+            IMPORT_OBJECT_NAME_NOPREFIX => ImportSymbol::Name(without_prefix().to_string()),
+            IMPORT_OBJECT_NAME_UNDECORATE => {
+                let name = without_prefix();
+                ImportSymbol::Name(name.split('@').next().unwrap_or(name).to_string())
+            }
+            IMPORT_OBJECT_NAME_EXPORTAS => {
+                ImportSymbol::Name(self.export_name.clone().unwrap_or_default())
+            }
+        }
+    }
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#import-type
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ImportObjectType {
+    IMPORT_OBJECT_CODE,
+    IMPORT_OBJECT_DATA,
+    IMPORT_OBJECT_CONST,
+}
+
+impl TryFrom<u16> for ImportObjectType {
+    type Error = PeError;
+    fn try_from(value: u16) -> Result<Self, PeError> {
+        match value {
+            0 => Ok(Self::IMPORT_OBJECT_CODE),
+            1 => Ok(Self::IMPORT_OBJECT_DATA),
+            2 => Ok(Self::IMPORT_OBJECT_CONST),
+            _ => Err(PeError::ParseError(format!(
+                "Invalid import object type: {:#x?}",
+                value
+            ))),
+        }
+    }
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#import-name-type
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ImportNameType {
+    IMPORT_OBJECT_ORDINAL,
+    IMPORT_OBJECT_NAME,
+    IMPORT_OBJECT_NAME_NOPREFIX,
+    IMPORT_OBJECT_NAME_UNDECORATE,
+    IMPORT_OBJECT_NAME_EXPORTAS,
+}
+
+impl TryFrom<u16> for ImportNameType {
+    type Error = PeError;
+    fn try_from(value: u16) -> Result<Self, PeError> {
+        match value {
+            0 => Ok(Self::IMPORT_OBJECT_ORDINAL),
+            1 => Ok(Self::IMPORT_OBJECT_NAME),
+            2 => Ok(Self::IMPORT_OBJECT_NAME_NOPREFIX),
+            3 => Ok(Self::IMPORT_OBJECT_NAME_UNDECORATE),
+            4 => Ok(Self::IMPORT_OBJECT_NAME_EXPORTAS),
+            _ => Err(PeError::ParseError(format!(
+                "Invalid import name type: {:#x?}",
+                value
+            ))),
+        }
+    }
+}
+
+struct MemberHeader<'a> {
+    name: &'a str,
+    date: u64,
+    size: usize,
+}
+
+/// The header fields are space padded ASCII, the numbers are decimal
+fn parse_member_header(bytes: &[u8], offset: usize) -> Result<MemberHeader<'_>, PeError> {
+    let header = slice_at(bytes, offset, MEMBER_HEADER_SIZE)?;
+    if &header[58..] != b"`\n" {
+        return Err(PeError::ParseError(format!(
+            "Invalid archive member header at {:#x}",
+            offset
+        )));
+    }
+    let field = |range: std::ops::Range<usize>| {
+        std::str::from_utf8(&header[range])
+            .map(|field| field.trim_end_matches(' '))
+            .map_err(|_| PeError::InvalidString { offset })
+    };
+    let number = |range: std::ops::Range<usize>| -> Result<u64, PeError> {
+        match field(range)? {
+            "" => Ok(0),
+            value => value.parse().map_err(|_| {
+                PeError::ParseError(format!(
+                    "Invalid number {:?} in the archive member header at {:#x}",
+                    value, offset
+                ))
+            }),
+        }
+    };
+    Ok(MemberHeader {
+        name: field(0..16)?,
+        date: number(16..28)?,
+        size: number(48..58)? as usize,
+    })
+}
+
+/// "/n" names are at offset n of the longnames member
+fn long_name(longnames: &[u8], index: &str) -> Result<String, PeError> {
+    let offset = index
+        .parse::<usize>()
+        .map_err(|_| PeError::ParseError(format!("Invalid long name offset: {}", index)))?;
+    let rest = longnames
+        .get(offset..)
+        .ok_or(PeError::OutOfBounds { offset, size: 1 })?;
+    let len = rest
+        .iter()
+        .position(|c| *c == 0 || *c == b'\n')
+        .unwrap_or(rest.len());
+    Ok(String::from_utf8_lossy(&rest[..len])
+        .trim_end_matches('/')
+        .to_string())
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#first-linker-member
+fn parse_first_linker_member(data: &[u8]) -> Result<Vec<ArchiveSymbol>, PeError> {
+    let mut cursor = Cursor::new(data);
+    //  the only big endian structure in the format
+    let count = cursor.read_u32()?.swap_bytes() as usize;
+    let offsets = cursor.read_slice(count.saturating_mul(4))?;
+    let mut names = cursor.offset();
+    offsets
+        .chunks_exact(4)
+        .map(|offset| {
+            let name = cstr_at(data, names)?;
+            names += name.len() + 1;
+            Ok(ArchiveSymbol {
+                name: String::from_utf8_lossy(name).to_string(),
+                member_offset: u32::from_be_bytes(offset.try_into().unwrap()),
+            })
+        })
+        .collect()
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#second-linker-member
+fn parse_second_linker_member(data: &[u8]) -> Result<Vec<ArchiveSymbol>, PeError> {
+    let mut cursor = Cursor::new(data);
+    let number_of_members = cursor.read_u32()? as usize;
+    let offsets = (0..number_of_members)
+        .map(|_| cursor.read_u32())
+        .collect::<Result<Vec<u32>, PeError>>()?;
+    let number_of_symbols = cursor.read_u32()? as usize;
+    let indices = cursor.read_slice(number_of_symbols.saturating_mul(2))?;
+    let mut names = cursor.offset();
+    indices
+        .chunks_exact(2)
+        .map(|index| {
+            //  one based index into the offsets
+            let index = u16::from_le_bytes(index.try_into().unwrap()) as usize;
+            let member_offset = *index
+                .checked_sub(1)
+                .and_then(|idx| offsets.get(idx))
+                .ok_or(PeError::ParseError(format!(
+                    "Invalid member index {} in the second linker member",
+                    index
+                )))?;
+            let name = cstr_at(data, names)?;
+            names += name.len() + 1;
+            Ok(ArchiveSymbol {
+                name: String::from_utf8_lossy(name).to_string(),
+                member_offset,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pe::{builder::ImportSymbol, file_header::Machine};
+
+    use super::{Archive, ImportNameType, ImportObjectType};
+
+    #[test]
+    fn reads_an_import_library() {
+        let lib = Archive::from_file("sample_import.lib").unwrap();
+        assert_eq!(lib.first_linker_member.len(), 12);
+        assert!(lib.second_linker_member.is_none());
+        assert_eq!(lib.members.len(), 8);
+        //  the name is longer than 15 bytes and comes from the longnames member
+        assert!(lib
+            .members
+            .iter()
+            .all(|member| member.name == "solaire_stub_library.dll"));
+
+        //  the import descriptor and the null thunks are full objects
+        let descriptor = lib
+            .member_for_symbol("__IMPORT_DESCRIPTOR_solaire_stub_library")
+            .unwrap();
+        assert!(!descriptor.is_import_object());
+        let object = descriptor.object().unwrap();
+        assert_eq!(
+            object.file_header.machine,
+            Machine::IMAGE_FILE_MACHINE_AMD64
+        );
+
+        let unload = lib
+            .member_for_symbol("__imp_unload")
+            .unwrap()
+            .import_object()
+            .unwrap();
+        assert_eq!(unload.machine, Machine::IMAGE_FILE_MACHINE_AMD64);
+        assert_eq!(unload.dll, "solaire_stub_library.dll");
+        assert_eq!(unload.ordinal_or_hint, 7);
+        assert_eq!(unload.r#type, ImportObjectType::IMPORT_OBJECT_CODE);
+        assert_eq!(
+            unload.import_symbol(),
+            ImportSymbol::Name("unload".to_string())
+        );
+
+        let imports = lib.imports().unwrap();
+        let symbols = imports
+            .iter()
+            .map(|import| import.import_symbol())
+            .collect::<Vec<_>>();
+        assert_eq!(symbols.len(), 5);
+        assert_eq!(symbols[2], ImportSymbol::Ordinal(9));
+        assert_eq!(imports[2].name_type, ImportNameType::IMPORT_OBJECT_ORDINAL);
+        assert_eq!(imports[3].r#type, ImportObjectType::IMPORT_OBJECT_DATA);
+    }
+
+    #[test]
+    fn reads_a_static_library() {
+        let lib = Archive::from_file("sample_static.lib").unwrap();
+        let member = lib.member_for_symbol("shellcode_entry").unwrap();
+        assert_eq!(member.name, "sample_object.obj");
+        let object = member.object().unwrap();
+        let text = object.get_section(".text").unwrap();
+        assert_eq!(text.raw_data.len(), 28);
+
+        assert!(Archive::try_from(&b"MZ"[..]).is_err());
+    }
+
+    /// The layout lib.exe writes: both linker members and null terminated long names.
+    /// `symbols` are (name, one based member index) and are written to both linker members,
+    /// except that the first one points every symbol at the first member, like a stale table
+    fn msvc_archive(objects: &[(&str, &[u8])], symbols: &[(&str, u16)]) -> Vec<u8> {
+        let header = |name: &str, size: usize| {
+            format!(
+                "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+                name, 0, "", "", 0, size
+            )
+            .into_bytes()
+        };
+        let names = symbols
+            .iter()
+            .flat_map(|(name, _)| [name.as_bytes(), b"\0"].concat())
+            .collect::<Vec<u8>>();
+        let mut longnames = vec![];
+        let mut name_offsets = vec![];
+        for (name, _) in objects {
+            name_offsets.push(longnames.len());
+            longnames.extend(name.as_bytes());
+            longnames.push(0);
+        }
+        let first_size = 4 + 4 * symbols.len() + names.len();
+        let second_size = 4 + 4 * objects.len() + 4 + 2 * symbols.len() + names.len();
+        let mut member_offsets = vec![
+            8 + 60
+                + first_size.next_multiple_of(2)
+                + 60
+                + second_size.next_multiple_of(2)
+                + 60
+                + longnames.len().next_multiple_of(2),
+        ];
+        for (_, object) in &objects[..objects.len() - 1] {
+            let last = *member_offsets.last().unwrap();
+            member_offsets.push(last + 60 + object.len().next_multiple_of(2));
+        }
+
+        let mut out = b"!<arch>\n".to_vec();
+        out.extend(header("/", first_size));
+        out.extend((symbols.len() as u32).to_be_bytes());
+        for _ in symbols {
+            out.extend((member_offsets[0] as u32).to_be_bytes());
+        }
+        out.extend(&names);
+        out.resize(out.len().next_multiple_of(2), b'\n');
+        out.extend(header("/", second_size));
+        out.extend((objects.len() as u32).to_le_bytes());
+        for offset in &member_offsets {
+            out.extend((*offset as u32).to_le_bytes());
+        }
+        out.extend((symbols.len() as u32).to_le_bytes());
+        for (_, index) in symbols {
+            out.extend(index.to_le_bytes());
+        }
+        out.extend(&names);
+        out.resize(out.len().next_multiple_of(2), b'\n');
+        out.extend(header("//", longnames.len()));
+        out.extend(&longnames);
+        out.resize(out.len().next_multiple_of(2), b'\n');
+        for ((_, object), name_offset) in objects.iter().zip(name_offsets) {
+            out.extend(header(&format!("/{}", name_offset), object.len()));
+            out.extend(*object);
+            out.resize(out.len().next_multiple_of(2), b'\n');
+        }
+        out
+    }
+
+    #[test]
+    fn reads_both_linker_members() {
+        let x64 = std::fs::read("sample_object.obj").unwrap();
+        let x86 = std::fs::read("sample_object_x86.obj").unwrap();
+        let objects = [
+            ("sample_object.obj", x64.as_slice()),
+            ("sample_object_x86.obj", x86.as_slice()),
+        ];
+        let symbols = [
+            ("_shellcode_entry", 2),
+            ("counter", 1),
+            ("shellcode_entry", 1),
+        ];
+        let lib = Archive::try_from(msvc_archive(&objects, &symbols).as_slice()).unwrap();
+        assert_eq!(lib.members.len(), 2);
+        assert_eq!(lib.members[1].name, "sample_object_x86.obj");
+
+        //  the one based indices resolve to the member offsets
+        let sorted = lib.second_linker_member.as_ref().unwrap();
+        assert_eq!(sorted.len(), 3);
+        assert_eq!(sorted[0].name, "_shellcode_entry");
+        assert_eq!(sorted[0].member_offset, lib.members[1].header_offset);
+        assert_eq!(sorted[2].member_offset, lib.members[0].header_offset);
+
+        //  the first linker member says member 1, the lookup goes through the sorted table
+        assert_eq!(
+            lib.first_linker_member[0].member_offset,
+            lib.members[0].header_offset
+        );
+        let member = lib.member_for_symbol("_shellcode_entry").unwrap();
+        assert_eq!(member.name, "sample_object_x86.obj");
+        assert_eq!(member.data, x86);
+        assert_eq!(
+            member.object().unwrap().file_header.machine,
+            Machine::IMAGE_FILE_MACHINE_I386
+        );
+        assert_eq!(
+            lib.member_for_symbol("counter").unwrap().name,
+            "sample_object.obj"
+        );
+
+        //  index 0 and indices past the member count are invalid
+        for index in [0, 3] {
+            let symbols = [("counter", index)];
+            assert!(Archive::try_from(msvc_archive(&objects, &symbols).as_slice()).is_err());
+        }
+    }
+}
@@ -0,0 +1,312 @@
+#![allow(non_camel_case_types)]
+
+use crate::util::{u16_from_bytes, u32_from_bytes, u64_from_bytes};
+
+use super::{
+    cursor::Cursor, import_table::rva2foa, optional_header::ImageDataDirectory,
+    section_table::SectionTable, PeError,
+};
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#the-reloc-section-image-only
+pub fn get_base_relocations(
+    section_table: &SectionTable,
+    bytes: &[u8],
+    reloc_table_dir: ImageDataDirectory,
+) -> Result<BaseRelocationTable, PeError> {
+    if reloc_table_dir.virtual_address == 0 || reloc_table_dir.size == 0 {
+        return Err(PeError::MissingTable(
+            "The executable doesn't have a base relocation table".to_string(),
+        ));
+    }
+    let reloc_foa = rva2foa(reloc_table_dir.virtual_address, section_table)? as usize;
+    let mut cursor = Cursor::from_slice(bytes, reloc_foa, reloc_table_dir.size as usize)?;
+
+    let mut blocks = vec![];
+    //  every block starts with an 8 byte header
+    while cursor.position + 8 <= cursor.bytes.len() {
+        let page_rva = cursor.read_u32()?;
+        let block_size = cursor.read_u32()?;
+        if block_size < 8 {
+            break;
+        }
+        let number_of_entries = (block_size as usize - 8) / 2;
+        let remaining = (cursor.bytes.len() - cursor.position) / 2;
+        let mut entries = Vec::with_capacity(number_of_entries.min(remaining));
+        let mut idx = 0;
+        while idx < number_of_entries {
+            let data = cursor.read_u16()?;
+            idx += 1;
+            let r#type = BaseRelocationType::from((data >> 12) as u8);
+            //  HIGHADJ takes up two slots, the second one holds the low 16 bits of the value
+            let param = match r#type {
+                BaseRelocationType::IMAGE_REL_BASED_HIGHADJ if idx < number_of_entries => {
+                    idx += 1;
+                    Some(cursor.read_u16()?)
+                }
+                _ => None,
+            };
+            entries.push(BaseRelocationEntry {
+                r#type,
+                offset: data & 0x0FFF,
+                param,
+            });
+        }
+        blocks.push(BaseRelocationBlock {
+            page_rva,
+            block_size,
+            entries,
+        });
+    }
+    Ok(BaseRelocationTable { blocks })
+}
+
+/// Applies every fixup in `table` to `buffer`, adding `delta` to the relocated values.
+/// `translate` maps the RVA of a fixup to an offset into `buffer`.
+pub fn apply_base_relocations(
+    buffer: &mut [u8],
+    table: &BaseRelocationTable,
+    delta: u64,
+    translate: impl Fn(u32) -> Result<usize, PeError>,
+) -> Result<(), PeError> {
+    use BaseRelocationType::*;
+    for block in &table.blocks {
+        for entry in &block.entries {
+            //  padding entries may point anywhere
+            if entry.r#type == IMAGE_REL_BASED_ABSOLUTE {
+                continue;
+            }
+            let offset = translate(entry.rva(block))?;
+            let out_of_bounds = || {
+                PeError::ParseError(format!(
+                    "Base relocation at rva {:#x} is out of bounds",
+                    entry.rva(block)
+                ))
+            };
+            match entry.r#type {
+                IMAGE_REL_BASED_ABSOLUTE => {}
+                IMAGE_REL_BASED_HIGHLOW => {
+                    let slot = buffer
+                        .get_mut(offset..offset + 4)
+                        .ok_or_else(out_of_bounds)?;
+                    let value = u32_from_bytes(slot).wrapping_add(delta as u32);
+                    slot.copy_from_slice(&value.to_le_bytes());
+                }
+                IMAGE_REL_BASED_DIR64 => {
+                    let slot = buffer
+                        .get_mut(offset..offset + 8)
+                        .ok_or_else(out_of_bounds)?;
+                    let value = u64_from_bytes(slot).wrapping_add(delta);
+                    slot.copy_from_slice(&value.to_le_bytes());
+                }
+                IMAGE_REL_BASED_HIGH => {
+                    let slot = buffer
+                        .get_mut(offset..offset + 2)
+                        .ok_or_else(out_of_bounds)?;
+                    let value = u16_from_bytes(slot).wrapping_add((delta >> 16) as u16);
+                    slot.copy_from_slice(&value.to_le_bytes());
+                }
+                IMAGE_REL_BASED_LOW => {
+                    let slot = buffer
+                        .get_mut(offset..offset + 2)
+                        .ok_or_else(out_of_bounds)?;
+                    let value = u16_from_bytes(slot).wrapping_add(delta as u16);
+                    slot.copy_from_slice(&value.to_le_bytes());
+                }
+                IMAGE_REL_BASED_HIGHADJ => {
+                    let slot = buffer
+                        .get_mut(offset..offset + 2)
+                        .ok_or_else(out_of_bounds)?;
+                    let full = ((u16_from_bytes(slot) as u32) << 16)
+                        .wrapping_add(entry.param.unwrap_or(0) as u32)
+                        .wrapping_add(delta as u32)
+                        .wrapping_add(0x8000);
+                    slot.copy_from_slice(&((full >> 16) as u16).to_le_bytes());
+                }
+                _ => {
+                    return Err(PeError::ParseError(format!(
+                        "Can't apply a base relocation of type {:?}",
+                        entry.r#type
+                    )))
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct BaseRelocationTable {
+    pub blocks: Vec<BaseRelocationBlock>,
+}
+
+impl BaseRelocationTable {
+    /// Every fixup as (rva, type), skipping the ABSOLUTE padding entries
+    pub fn fixups(&self) -> impl Iterator<Item = (u32, &BaseRelocationType)> {
+        self.blocks.iter().flat_map(|block| {
+            block
+                .entries
+                .iter()
+                .filter(|e| e.r#type != BaseRelocationType::IMAGE_REL_BASED_ABSOLUTE)
+                .map(move |e| (e.rva(block), &e.r#type))
+        })
+    }
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#base-relocation-block
+#[derive(Debug, Clone)]
+pub struct BaseRelocationBlock {
+    pub page_rva: u32,
+    pub block_size: u32,
+    pub entries: Vec<BaseRelocationEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BaseRelocationEntry {
+    pub r#type: BaseRelocationType,
+    /// Offset from the page RVA of the block
+    pub offset: u16,
+    /// The low 16 bits of the target for IMAGE_REL_BASED_HIGHADJ, stored in the next slot
+    pub param: Option<u16>,
+}
+
+impl BaseRelocationEntry {
+    /// Wraps around for page RVAs at the top of the address space instead of overflowing
+    pub fn rva(&self, block: &BaseRelocationBlock) -> u32 {
+        block.page_rva.wrapping_add(self.offset as u32)
+    }
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#base-relocation-types
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BaseRelocationType {
+    IMAGE_REL_BASED_ABSOLUTE,
+    IMAGE_REL_BASED_HIGH,
+    IMAGE_REL_BASED_LOW,
+    IMAGE_REL_BASED_HIGHLOW,
+    IMAGE_REL_BASED_HIGHADJ,
+    /// Also IMAGE_REL_BASED_MIPS_JMPADDR and IMAGE_REL_BASED_RISCV_HIGH20, depending on the machine
+    IMAGE_REL_BASED_ARM_MOV32,
+    IMAGE_REL_BASED_RESERVED,
+    /// Also IMAGE_REL_BASED_RISCV_LOW12I, depending on the machine
+    IMAGE_REL_BASED_THUMB_MOV32,
+    /// Also IMAGE_REL_BASED_LOONGARCH32_MARK_LA and IMAGE_REL_BASED_LOONGARCH64_MARK_LA
+    IMAGE_REL_BASED_RISCV_LOW12S,
+    IMAGE_REL_BASED_MIPS_JMPADDR16,
+    IMAGE_REL_BASED_DIR64,
+    /// Types this parser doesn't know, they're kept so the rest of the table can be read
+    Other(u8),
+}
+
+impl From<u8> for BaseRelocationType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::IMAGE_REL_BASED_ABSOLUTE,
+            1 => Self::IMAGE_REL_BASED_HIGH,
+            2 => Self::IMAGE_REL_BASED_LOW,
+            3 => Self::IMAGE_REL_BASED_HIGHLOW,
+            4 => Self::IMAGE_REL_BASED_HIGHADJ,
+            5 => Self::IMAGE_REL_BASED_ARM_MOV32,
+            6 => Self::IMAGE_REL_BASED_RESERVED,
+            7 => Self::IMAGE_REL_BASED_THUMB_MOV32,
+            8 => Self::IMAGE_REL_BASED_RISCV_LOW12S,
+            9 => Self::IMAGE_REL_BASED_MIPS_JMPADDR16,
+            10 => Self::IMAGE_REL_BASED_DIR64,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        pe::{
+            optional_header::{ImageBase, ImageDataDirectory},
+            test_util::{put_u16, put_u32, section, section_table},
+            PortableExecutable,
+        },
+        util::{u32_from_bytes, u64_from_bytes},
+    };
+
+    use super::{get_base_relocations, BaseRelocationType};
+
+    #[test]
+    fn parses_blocks() {
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        let table = pe.base_relocations().unwrap();
+        let first = &table.blocks[0];
+        assert_eq!(first.page_rva, 0x2000);
+        assert_eq!(first.block_size, 0xc);
+        assert_eq!(
+            first.entries[0].r#type,
+            BaseRelocationType::IMAGE_REL_BASED_DIR64
+        );
+        assert_eq!(first.entries[0].rva(first), 0x2698);
+        assert_eq!(
+            first.entries[1].r#type,
+            BaseRelocationType::IMAGE_REL_BASED_ABSOLUTE
+        );
+
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        let table = pe.base_relocations().unwrap();
+        assert_eq!(table.blocks.len(), 9);
+        assert!(table
+            .fixups()
+            .all(|(_, t)| *t == BaseRelocationType::IMAGE_REL_BASED_HIGHLOW));
+    }
+
+    #[test]
+    fn keeps_crafted_blocks() {
+        let section_table = section_table([section(".reloc", 0x1000, 0, 0x10)]);
+        let mut bytes = vec![0u8; 0x10];
+        //  a page at the top of the address space with an unknown type and a DIR64
+        put_u32(&mut bytes, 0, 0xffffff00);
+        put_u32(&mut bytes, 4, 12);
+        put_u16(&mut bytes, 8, 0xb200);
+        put_u16(&mut bytes, 10, 0xa200);
+        let dir = ImageDataDirectory {
+            virtual_address: 0x1000,
+            size: 12,
+            tag: "base_relocation_table".to_string(),
+        };
+        let table = get_base_relocations(&section_table, &bytes, dir).unwrap();
+        assert_eq!(
+            table.fixups().collect::<Vec<_>>(),
+            [
+                (0x100, &BaseRelocationType::Other(0xb)),
+                (0x100, &BaseRelocationType::IMAGE_REL_BASED_DIR64)
+            ]
+        );
+    }
+
+    #[test]
+    fn rebase_x64() {
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        let bytes = pe.rebase(0x7ff6_1234_0000).unwrap();
+        let original = std::fs::read("sample_executable.exe").unwrap();
+        let offset = pe.rva_to_offset(0x2698).unwrap();
+        assert_eq!(
+            u64_from_bytes(&bytes[offset..]) - u64_from_bytes(&original[offset..]),
+            0x7ff6_1234_0000 - 0x1_4000_0000
+        );
+
+        let rebased = PortableExecutable::try_from(bytes).unwrap();
+        assert!(matches!(
+            rebased.nt_headers.opt_header.win_specific_fields.image_base,
+            ImageBase::PE32_PLUS(0x7ff6_1234_0000)
+        ));
+    }
+
+    #[test]
+    fn rebase_x86() {
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        assert!(pe.rebase(0x1_0000_0000).is_err());
+
+        let bytes = pe.rebase(0x10000000).unwrap();
+        let original = std::fs::read("sample_executable_x86.exe").unwrap();
+        let offset = pe.rva_to_offset(0x11798).unwrap();
+        assert_eq!(
+            u32_from_bytes(&bytes[offset..]) - u32_from_bytes(&original[offset..]),
+            0x10000000 - 0x400000
+        );
+    }
+}
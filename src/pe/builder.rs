@@ -0,0 +1,976 @@
+use crate::util::{u32_from_bytes, u64_from_bytes};
+
+use super::{
+    checksum::{pe_checksum, CHECKSUM_OFFSET},
+    cursor::{slice_at, u32_at},
+    import_table::rva2foa,
+    optional_header::{ExecutableKind, ImageDirectoryEntry},
+    section_table::{SectionFlags, SectionHeader, SectionTable},
+    view::PeView,
+    NtHeaders, PeError, PortableExecutable,
+};
+
+const IMPORT_SECTION_NAME: &str = ".idata2";
+
+/// Edits a parsed PE and serializes it back into a valid image.
+/// Sections are laid out back to back in the file, RVAs of existing sections never move
+#[derive(Debug, Clone)]
+pub struct PeBuilder {
+    /// The original headers, the DOS stub, Rich header and anything in the header slack are kept
+    headers: Vec<u8>,
+    e_lfanew: usize,
+    pub nt_headers: NtHeaders,
+    pub sections: Vec<BuilderSection>,
+    /// Data past the last section, the certificate table lives here
+    pub overlay: Vec<u8>,
+    overlay_offset: usize,
+    import_descriptors: Vec<u8>,
+    imports: Vec<PendingImport>,
+}
+
+/// A function to import, by name or by ordinal
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ImportSymbol {
+    Name(String),
+    Ordinal(u16),
+}
+
+#[derive(Debug, Clone)]
+struct PendingImport {
+    dll: String,
+    symbols: Vec<ImportSymbol>,
+    /// An existing IAT to reuse instead of allocating one, the code references it
+    iat_rva: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BuilderSection {
+    /// `ptr_to_raw_data` and `size_of_raw_data` are recomputed on build
+    pub header: SectionHeader,
+    pub data: Vec<u8>,
+}
+
+impl PeBuilder {
+    pub fn new(pe: &PortableExecutable) -> Result<Self, PeError> {
+        let bytes = &pe.bytes;
+        let size_of_headers = pe.nt_headers.opt_header.win_specific_fields.size_of_headers;
+        let headers = slice_at(bytes, 0, (size_of_headers as usize).min(bytes.len()))?.to_vec();
+        let sections = pe
+            .section_table
+            .section_headers
+            .iter()
+            .map(|header| BuilderSection {
+                header: SectionHeader {
+                    raw_data: vec![],
+                    ..header.clone()
+                },
+                data: header.raw_data.clone(),
+            })
+            .collect::<Vec<BuilderSection>>();
+        let overlay_offset = pe.sections_end();
+
+        //  keep the existing descriptors around in case an import gets appended
+        let import_dir = pe.get_image_directory(ImageDirectoryEntry::IMPORT);
+        let import_descriptors = match import_dir.virtual_address {
+            0 => vec![],
+            rva => read_import_descriptors(bytes, rva2foa(rva, &pe.section_table)? as usize)?,
+        };
+
+        Ok(PeBuilder {
+            headers,
+            e_lfanew: pe.dos_header.e_lfanew as usize,
+            nt_headers: pe.nt_headers.clone(),
+            sections,
+            overlay: bytes[overlay_offset..].to_vec(),
+            overlay_offset,
+            import_descriptors,
+            imports: vec![],
+        })
+    }
+
+    /// Starts from a module read out of a live process. Sections are taken from their RVAs,
+    /// the raw pointers in the dumped headers are ignored and recomputed on build.
+    /// The loader already wrote the actual base in the headers, so the result is consistent
+    /// with the relocated code
+    pub fn from_image(image: &[u8]) -> Result<Self, PeError> {
+        let view = PeView::parse(image)?;
+        let size_of_headers = view
+            .nt_headers
+            .opt_header
+            .win_specific_fields
+            .size_of_headers;
+        let headers = slice_at(image, 0, (size_of_headers as usize).min(image.len()))?.to_vec();
+        let sections = view
+            .section_table
+            .section_headers
+            .iter()
+            .map(|header| {
+                let start = (header.virtual_address as usize).min(image.len());
+                let end = start
+                    .saturating_add(header.virtual_extent() as usize)
+                    .min(image.len());
+                let data = &image[start..end];
+                //  trailing zeroes don't need file space, the loader zero fills
+                let len = data
+                    .iter()
+                    .rposition(|b| *b != 0)
+                    .map_or(0, |last| last + 1);
+                BuilderSection {
+                    header: SectionHeader {
+                        raw_data: vec![],
+                        ..header.clone()
+                    },
+                    data: data[..len].to_vec(),
+                }
+            })
+            .collect::<Vec<BuilderSection>>();
+
+        let mut nt_headers = view.nt_headers.clone();
+        let dirs = &mut nt_headers.opt_header.data_directories;
+        //  the certificate table isn't mapped
+        let security = &mut dirs[ImageDirectoryEntry::SECURITY as usize];
+        security.virtual_address = 0;
+        security.size = 0;
+        //  packers often leave an invalid import directory behind, see `rebuild_imports`
+        let import_descriptors = match dirs[ImageDirectoryEntry::IMPORT as usize].virtual_address {
+            0 => vec![],
+            rva => read_import_descriptors(image, rva as usize).unwrap_or_default(),
+        };
+
+        Ok(PeBuilder {
+            headers,
+            e_lfanew: view.dos_header.e_lfanew as usize,
+            nt_headers,
+            sections,
+            overlay: vec![],
+            overlay_offset: image.len(),
+            import_descriptors,
+            imports: vec![],
+        })
+    }
+
+    pub fn section_mut(&mut self, name: &str) -> Option<&mut BuilderSection> {
+        self.sections
+            .iter_mut()
+            .find(|section| section.header.name() == name)
+    }
+
+    /// Appends a section right after the last one in memory
+    pub fn add_section(
+        &mut self,
+        name: &str,
+        data: Vec<u8>,
+        characteristics: SectionFlags,
+    ) -> Result<&mut BuilderSection, PeError> {
+        if name.len() > 8 {
+            return Err(PeError::ParseError(format!(
+                "Section names are limited to 8 bytes: {}",
+                name
+            )));
+        }
+        let virtual_address = self.next_virtual_address()?;
+        self.sections.push(BuilderSection {
+            header: SectionHeader {
+                name: name.to_string(),
+                virtual_size: data.len() as u32,
+                virtual_address,
+                size_of_raw_data: 0,
+                ptr_to_raw_data: 0,
+                ptr_to_relocations: 0,
+                ptr_to_linenumbers: 0,
+                number_of_relocations: 0,
+                number_of_linenumbers: 0,
+                characteristics,
+                raw_data: vec![],
+            },
+            data,
+        });
+        Ok(self.sections.last_mut().unwrap())
+    }
+
+    /// Sections can't leave a hole in memory, so the previous one grows to cover the gap.
+    /// Data directories that point into the section have to be cleared first
+    pub fn remove_section(&mut self, name: &str) -> Result<BuilderSection, PeError> {
+        let idx = self.section_index(name)?;
+        if idx == 0 && self.sections.len() > 1 {
+            return Err(PeError::ParseError(
+                "Can't remove the first section, it would leave a gap after the headers"
+                    .to_string(),
+            ));
+        }
+        let header = &self.sections[idx].header;
+        let start = header.virtual_address;
+        let end = start.saturating_add(header.virtual_extent());
+        let pointing_in = self
+            .nt_headers
+            .opt_header
+            .data_directories
+            .iter()
+            .enumerate()
+            //  the certificate table is addressed by file offset
+            .filter(|(entry, _)| *entry != ImageDirectoryEntry::SECURITY as usize)
+            .map(|(_, dir)| dir)
+            .find(|dir| {
+                dir.size != 0
+                    && dir.virtual_address < end
+                    && dir.virtual_address.saturating_add(dir.size) > start
+            });
+        if let Some(dir) = pointing_in {
+            return Err(PeError::ParseError(format!(
+                "Can't remove {}, the {} directory still points into it",
+                name, dir.tag
+            )));
+        }
+        let removed = self.sections.remove(idx);
+        if idx < self.sections.len() {
+            let next_va = self.sections[idx].header.virtual_address;
+            let previous = &mut self.sections[idx - 1].header;
+            previous.virtual_size = next_va - previous.virtual_address;
+        }
+        Ok(removed)
+    }
+
+    /// Grows or shrinks the section's data, it can't grow into the next section's RVAs
+    pub fn resize_section(&mut self, name: &str, new_size: u32) -> Result<(), PeError> {
+        let idx = self.section_index(name)?;
+        let header = &self.sections[idx].header;
+        let end = aligned_end(header.virtual_address, new_size, self.section_alignment())?;
+        if let Some(next) = self.sections.get(idx + 1) {
+            if end > next.header.virtual_address {
+                return Err(PeError::ParseError(format!(
+                    "Resizing {} to {:#x} would overlap {}",
+                    name,
+                    new_size,
+                    next.header.name()
+                )));
+            }
+        }
+        let section = &mut self.sections[idx];
+        section.data.resize(new_size as usize, 0);
+        section.header.virtual_size = new_size;
+        Ok(())
+    }
+
+    /// Queues an import descriptor, the import directory is rebuilt in a new section on build
+    pub fn append_import(&mut self, dll: &str, functions: &[&str]) {
+        self.imports.push(PendingImport {
+            dll: dll.to_string(),
+            symbols: functions
+                .iter()
+                .map(|function| ImportSymbol::Name(function.to_string()))
+                .collect(),
+            iat_rva: None,
+        });
+    }
+
+    /// Replaces the import directory with one generated from a resolved IAT, for dumps whose
+    /// imports were destroyed by a packer. `resolve` maps an IAT value to the DLL and function
+    /// it points to. The new descriptors reuse the existing IAT so the code doesn't change,
+    /// a new descriptor starts at every null slot or DLL change
+    pub fn rebuild_imports(
+        &mut self,
+        iat_rva: u32,
+        iat_size: u32,
+        mut resolve: impl FnMut(u64) -> Option<(String, ImportSymbol)>,
+    ) -> Result<(), PeError> {
+        let thunk_size = self.thunk_size();
+        //  both come from a dump, don't trust them to stay in the address space
+        if iat_rva.checked_add(iat_size).is_none() {
+            return Err(PeError::ParseError(format!(
+                "The IAT at {:#x} with a size of {:#x} doesn't fit in the address space",
+                iat_rva, iat_size
+            )));
+        }
+        let iat = self.data_at_rva(iat_rva, iat_size as usize)?;
+        let mut imports = vec![];
+        let mut current: Option<PendingImport> = None;
+        for (idx, slot) in iat.chunks_exact(thunk_size).enumerate() {
+            let slot_rva = iat_rva
+                .checked_add((idx * thunk_size) as u32)
+                .ok_or(PeError::UnmappedAddress { rva: iat_rva })?;
+            let value = match thunk_size {
+                4 => u32_from_bytes(slot) as u64,
+                _ => u64_from_bytes(slot),
+            };
+            if value == 0 {
+                imports.extend(current.take());
+                continue;
+            }
+            let (dll, symbol) = resolve(value).ok_or(PeError::ParseError(format!(
+                "Could not resolve the IAT entry at {:#x}: {:#x}",
+                slot_rva, value
+            )))?;
+            match &mut current {
+                Some(import) if import.dll.eq_ignore_ascii_case(&dll) => {
+                    import.symbols.push(symbol)
+                }
+                _ => imports.extend(current.replace(PendingImport {
+                    dll,
+                    symbols: vec![symbol],
+                    iat_rva: Some(slot_rva),
+                })),
+            }
+        }
+        imports.extend(current);
+
+        self.import_descriptors.clear();
+        self.imports = imports;
+        let iat_dir =
+            &mut self.nt_headers.opt_header.data_directories[ImageDirectoryEntry::IAT as usize];
+        iat_dir.virtual_address = iat_rva;
+        iat_dir.size = iat_size;
+        Ok(())
+    }
+
+    pub fn write(&self, path: impl AsRef<std::path::Path>) -> Result<(), PeError> {
+        Ok(std::fs::write(path, self.build()?)?)
+    }
+
+    pub fn build(&self) -> Result<Vec<u8>, PeError> {
+        let mut builder = self.clone();
+        if !builder.imports.is_empty() {
+            builder.emit_imports()?;
+        }
+        builder.layout()
+    }
+
+    fn layout(mut self) -> Result<Vec<u8>, PeError> {
+        let file_alignment = self.file_alignment();
+        let section_alignment = self.section_alignment();
+        let opt_header_size = self.nt_headers.opt_header.to_bytes().len();
+        let section_table_offset = self.e_lfanew + 4 + 20 + opt_header_size;
+        let headers_end = section_table_offset + self.sections.len() * 40;
+        let size_of_headers = align_up(headers_end.max(self.headers.len()) as u32, file_alignment);
+        //  the loader maps the headers at the image base, they can't run into the first section
+        if let Some(first) = self.sections.first() {
+            if align_up(size_of_headers, section_alignment) > first.header.virtual_address {
+                return Err(PeError::ParseError(format!(
+                    "The headers need {:#x} bytes but the first section starts at {:#x}",
+                    size_of_headers, first.header.virtual_address
+                )));
+            }
+        }
+
+        let mut offset = size_of_headers;
+        for section in &mut self.sections {
+            let header = &mut section.header;
+            header.size_of_raw_data = align_up(section.data.len() as u32, file_alignment);
+            header.ptr_to_raw_data = match header.size_of_raw_data {
+                0 => 0,
+                _ => offset,
+            };
+            offset += header.size_of_raw_data;
+        }
+        let overlay_offset = offset as usize;
+
+        let file_header = &mut self.nt_headers.file_header;
+        file_header.number_of_sections = self.sections.len() as u16;
+        file_header.size_of_optional_header = opt_header_size as u16;
+
+        let opt_header = &mut self.nt_headers.opt_header;
+        let std = &mut opt_header.std_fields;
+        let sum_sizes = |flag: SectionFlags, size: fn(&SectionHeader) -> u32| {
+            self.sections
+                .iter()
+                .filter(|section| section.header.characteristics.contains(flag))
+                .map(|section| size(&section.header))
+                .sum::<u32>()
+        };
+        std.size_of_code = sum_sizes(SectionFlags::IMAGE_SCN_CNT_CODE, |h| h.size_of_raw_data);
+        std.size_of_initialized_data =
+            sum_sizes(SectionFlags::IMAGE_SCN_CNT_INITIALIZED_DATA, |h| {
+                h.size_of_raw_data
+            });
+        std.size_of_uninitialized_data =
+            sum_sizes(SectionFlags::IMAGE_SCN_CNT_UNINITIALIZED_DATA, |h| {
+                h.virtual_size
+            });
+        let win = &mut opt_header.win_specific_fields;
+        win.size_of_headers = size_of_headers;
+        win.size_of_image = match self.sections.last() {
+            Some(section) => {
+                let header = &section.header;
+                aligned_end(
+                    header.virtual_address,
+                    header.virtual_size.max(header.size_of_raw_data),
+                    section_alignment,
+                )?
+            }
+            None => align_up(size_of_headers, section_alignment),
+        };
+        win.checksum = 0;
+
+        let dirs = &mut opt_header.data_directories;
+        //  bound imports live in the header slack and the bigger section table may overwrite them
+        let bound = &mut dirs[ImageDirectoryEntry::BOUND_IMPORT as usize];
+        if bound.size != 0 && (bound.virtual_address as usize) < headers_end {
+            bound.virtual_address = 0;
+            bound.size = 0;
+        }
+        //  the certificate table is addressed by file offset and moves with the overlay
+        let security = &mut dirs[ImageDirectoryEntry::SECURITY as usize];
+        if security.size != 0 && security.virtual_address as usize >= self.overlay_offset {
+            security.virtual_address =
+                (security.virtual_address as usize - self.overlay_offset + overlay_offset) as u32;
+        }
+        //  so does a COFF symbol table left after the sections
+        let file_header = &mut self.nt_headers.file_header;
+        if file_header.ptr_to_symbol_table != 0
+            && file_header.ptr_to_symbol_table as usize >= self.overlay_offset
+        {
+            file_header.ptr_to_symbol_table = (file_header.ptr_to_symbol_table as usize
+                - self.overlay_offset
+                + overlay_offset) as u32;
+        }
+
+        let mut out = self.headers.clone();
+        out.resize(size_of_headers as usize, 0);
+        out[self.e_lfanew..section_table_offset].fill(0);
+        out[self.e_lfanew..self.e_lfanew + 4].copy_from_slice(b"PE\0\0");
+        out[self.e_lfanew + 4..self.e_lfanew + 24]
+            .copy_from_slice(&self.nt_headers.file_header.to_bytes());
+        out[self.e_lfanew + 24..section_table_offset]
+            .copy_from_slice(&self.nt_headers.opt_header.to_bytes());
+        //  clear the old section table in case the new one is shorter
+        let old_headers_end = self.e_lfanew
+            + 24
+            + u32_at(&self.headers, self.e_lfanew + 20).map_or(0, |v| (v & 0xffff) as usize)
+            + u32_at(&self.headers, self.e_lfanew + 4).map_or(0, |v| (v >> 16) as usize) * 40;
+        let clear_end = old_headers_end.clamp(headers_end, size_of_headers as usize);
+        out[headers_end..clear_end].fill(0);
+        for (idx, section) in self.sections.iter().enumerate() {
+            let offset = section_table_offset + idx * 40;
+            out[offset..offset + 40].copy_from_slice(&section.header.to_bytes());
+        }
+
+        for section in &self.sections {
+            out.extend(&section.data);
+            out.resize(out.len().next_multiple_of(file_alignment as usize), 0);
+        }
+        out.extend(&self.overlay);
+
+        self.fix_debug_directory(&mut out)?;
+        let checksum_offset = self.e_lfanew + 24 + CHECKSUM_OFFSET;
+        let checksum = pe_checksum(&out, checksum_offset);
+        out[checksum_offset..checksum_offset + 4].copy_from_slice(&checksum.to_le_bytes());
+        Ok(out)
+    }
+
+    /// Debug entries point at their data by file offset too
+    fn fix_debug_directory(&self, out: &mut [u8]) -> Result<(), PeError> {
+        let debug_dir =
+            &self.nt_headers.opt_header.data_directories[ImageDirectoryEntry::DEBUG as usize];
+        if debug_dir.virtual_address == 0 || debug_dir.size == 0 {
+            return Ok(());
+        }
+        let section_table = self.section_table();
+        let debug_foa = rva2foa(debug_dir.virtual_address, &section_table)? as usize;
+        for entry in 0..debug_dir.size as usize / 28 {
+            let entry_offset = debug_foa + entry * 28;
+            let address_of_raw_data = u32_at(out, entry_offset + 20)?;
+            if address_of_raw_data != 0 {
+                let ptr_to_raw_data = rva2foa(address_of_raw_data, &section_table)?;
+                out[entry_offset + 24..entry_offset + 28]
+                    .copy_from_slice(&ptr_to_raw_data.to_le_bytes());
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies the existing descriptors next to the new ones, the original thunks stay in place
+    fn emit_imports(&mut self) -> Result<(), PeError> {
+        let thunk_size = self.thunk_size();
+        let ordinal_flag = match thunk_size {
+            4 => 0x80000000,
+            _ => 0x8000000000000000,
+        };
+        let section_va = self.next_virtual_address()?;
+        let descriptors_size = self.import_descriptors.len() + (self.imports.len() + 1) * 20;
+
+        //  descriptors, then per DLL: lookup table, address table, hint/name entries and name
+        let mut data = vec![0u8; descriptors_size];
+        data[..self.import_descriptors.len()].copy_from_slice(&self.import_descriptors);
+        let mut existing_iats = vec![];
+        for (idx, import) in self.imports.iter().enumerate() {
+            let thunks_size = (import.symbols.len() + 1) * thunk_size;
+            let ilt_offset = data.len();
+            data.resize(ilt_offset + thunks_size, 0);
+            let iat_offset = match import.iat_rva {
+                Some(_) => None,
+                None => {
+                    data.resize(data.len() + thunks_size, 0);
+                    Some(ilt_offset + thunks_size)
+                }
+            };
+            let mut thunks = vec![];
+            for symbol in &import.symbols {
+                let thunk = match symbol {
+                    ImportSymbol::Ordinal(ordinal) => ordinal_flag | *ordinal as u64,
+                    ImportSymbol::Name(name) => {
+                        let hint_name_rva = section_va + data.len() as u32;
+                        data.extend(0u16.to_le_bytes());
+                        data.extend(name.as_bytes());
+                        data.push(0);
+                        if !data.len().is_multiple_of(2) {
+                            data.push(0);
+                        }
+                        hint_name_rva as u64
+                    }
+                };
+                thunks.extend(&thunk.to_le_bytes()[..thunk_size]);
+            }
+            for table_offset in [Some(ilt_offset), iat_offset].into_iter().flatten() {
+                data[table_offset..table_offset + thunks.len()].copy_from_slice(&thunks);
+            }
+            let iat_rva = match import.iat_rva {
+                Some(iat_rva) => {
+                    //  on disk the IAT mirrors the lookup table
+                    existing_iats.push((iat_rva, thunks));
+                    iat_rva
+                }
+                None => section_va + iat_offset.unwrap_or_default() as u32,
+            };
+            let name_rva = section_va + data.len() as u32;
+            data.extend(import.dll.as_bytes());
+            data.push(0);
+
+            let descriptor_offset = self.import_descriptors.len() + idx * 20;
+            let descriptor = &mut data[descriptor_offset..descriptor_offset + 20];
+            descriptor[0..4].copy_from_slice(&(section_va + ilt_offset as u32).to_le_bytes());
+            descriptor[12..16].copy_from_slice(&name_rva.to_le_bytes());
+            descriptor[16..20].copy_from_slice(&iat_rva.to_le_bytes());
+        }
+        for (iat_rva, thunks) in existing_iats {
+            self.data_at_rva_mut(iat_rva, thunks.len())?
+                .copy_from_slice(&thunks);
+        }
+
+        //  the loader writes the resolved addresses into the IAT
+        self.add_section(
+            IMPORT_SECTION_NAME,
+            data,
+            SectionFlags::IMAGE_SCN_CNT_INITIALIZED_DATA
+                | SectionFlags::IMAGE_SCN_MEM_READ
+                | SectionFlags::IMAGE_SCN_MEM_WRITE,
+        )?;
+        let import_dir =
+            &mut self.nt_headers.opt_header.data_directories[ImageDirectoryEntry::IMPORT as usize];
+        import_dir.virtual_address = section_va;
+        import_dir.size = descriptors_size as u32;
+        self.imports.clear();
+        Ok(())
+    }
+
+    /// Reads the zero filled tail of the section as zeros, `from_image` trims it
+    fn data_at_rva(&self, rva: u32, size: usize) -> Result<Vec<u8>, PeError> {
+        let section = self
+            .sections
+            .iter()
+            .find(|section| {
+                rva >= section.header.virtual_address
+                    && rva - section.header.virtual_address < section.header.virtual_extent()
+            })
+            .ok_or(PeError::UnmappedAddress { rva })?;
+        let start = (rva - section.header.virtual_address) as usize;
+        let end = start + size;
+        if end > section.header.virtual_extent() as usize {
+            return Err(PeError::OutOfBounds {
+                offset: start,
+                size,
+            });
+        }
+        let mut data = section
+            .data
+            .get(start.min(section.data.len())..end.min(section.data.len()))
+            .unwrap_or_default()
+            .to_vec();
+        data.resize(size, 0);
+        Ok(data)
+    }
+
+    /// Grows the section's data if the range is in its zero filled tail
+    fn data_at_rva_mut(&mut self, rva: u32, size: usize) -> Result<&mut [u8], PeError> {
+        let section = self
+            .sections
+            .iter_mut()
+            .find(|section| {
+                rva >= section.header.virtual_address
+                    && rva - section.header.virtual_address < section.header.virtual_extent()
+            })
+            .ok_or(PeError::UnmappedAddress { rva })?;
+        let start = (rva - section.header.virtual_address) as usize;
+        let end = start + size;
+        if end > section.header.virtual_extent() as usize {
+            return Err(PeError::OutOfBounds {
+                offset: start,
+                size,
+            });
+        }
+        if end > section.data.len() {
+            section.data.resize(end, 0);
+        }
+        Ok(&mut section.data[start..end])
+    }
+
+    fn thunk_size(&self) -> usize {
+        match self.nt_headers.opt_header.std_fields.magic {
+            ExecutableKind::PE32 => 4,
+            ExecutableKind::PE32_PLUS => 8,
+        }
+    }
+
+    fn section_index(&self, name: &str) -> Result<usize, PeError> {
+        self.sections
+            .iter()
+            .position(|section| section.header.name() == name)
+            .ok_or(PeError::MissingSection(format!(
+                "Could not find the {} section",
+                name
+            )))
+    }
+
+    fn section_table(&self) -> SectionTable {
+        SectionTable {
+            section_headers: self
+                .sections
+                .iter()
+                .map(|section| section.header.clone())
+                .collect(),
+        }
+    }
+
+    fn next_virtual_address(&self) -> Result<u32, PeError> {
+        match self.sections.last() {
+            Some(section) => aligned_end(
+                section.header.virtual_address,
+                section.header.virtual_size,
+                self.section_alignment(),
+            ),
+            None => Ok(align_up(
+                self.nt_headers
+                    .opt_header
+                    .win_specific_fields
+                    .size_of_headers,
+                self.section_alignment(),
+            )),
+        }
+    }
+
+    fn file_alignment(&self) -> u32 {
+        self.nt_headers
+            .opt_header
+            .win_specific_fields
+            .file_alignment
+            .max(1)
+    }
+
+    fn section_alignment(&self) -> u32 {
+        self.nt_headers
+            .opt_header
+            .win_specific_fields
+            .section_alignment
+            .max(1)
+    }
+}
+
+/// The import descriptors at `offset`, without the null terminator
+fn read_import_descriptors(bytes: &[u8], mut offset: usize) -> Result<Vec<u8>, PeError> {
+    let mut descriptors = vec![];
+    loop {
+        let descriptor = slice_at(bytes, offset, 20)?;
+        if descriptor.iter().all(|b| *b == 0) {
+            return Ok(descriptors);
+        }
+        descriptors.extend(descriptor);
+        offset += 20;
+    }
+}
+
+fn align_up(value: u32, alignment: u32) -> u32 {
+    value.div_ceil(alignment) * alignment
+}
+
+/// `start + size` aligned up, fails instead of overflowing past the 4GB address space
+fn aligned_end(start: u32, size: u32, alignment: u32) -> Result<u32, PeError> {
+    start
+        .checked_add(size)
+        .and_then(|end| end.checked_next_multiple_of(alignment))
+        .ok_or(PeError::ParseError(format!(
+            "{:#x} + {:#x} doesn't fit in the address space",
+            start, size
+        )))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pe::{
+        import_table::FuncAddress, loader::MapOptions, optional_header::ImageDirectoryEntry,
+        section_table::SectionFlags, PortableExecutable,
+    };
+
+    use super::{ImportSymbol, PeBuilder};
+
+    #[test]
+    fn round_trips_unchanged() {
+        for path in ["sample_executable.exe", "sample_executable_x86.exe"] {
+            let pe = PortableExecutable::from_file(path).unwrap();
+            let bytes = pe.builder().unwrap().build().unwrap();
+            let rebuilt = PortableExecutable::try_from(bytes.clone()).unwrap();
+            rebuilt.verify_checksum().unwrap();
+
+            for (old, new) in pe
+                .section_table
+                .section_headers
+                .iter()
+                .zip(&rebuilt.section_table.section_headers)
+            {
+                assert_eq!(old.name, new.name);
+                assert_eq!(old.virtual_address, new.virtual_address);
+                assert_eq!(old.raw_data, new.raw_data);
+            }
+            assert_eq!(
+                pe.get_import_table().unwrap().image_descriptors.len(),
+                rebuilt.get_import_table().unwrap().image_descriptors.len()
+            );
+            if let Ok(codeview) = pe.codeview() {
+                assert_eq!(codeview, rebuilt.codeview().unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn adds_a_section_and_an_import() {
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        let mut builder = pe.builder().unwrap();
+        let stub = vec![0xcc; 0x1234];
+        builder
+            .add_section(
+                ".solaire",
+                stub.clone(),
+                SectionFlags::IMAGE_SCN_CNT_CODE
+                    | SectionFlags::IMAGE_SCN_MEM_EXECUTE
+                    | SectionFlags::IMAGE_SCN_MEM_READ,
+            )
+            .unwrap();
+        builder.append_import("solaire.dll", &["load", "unload"]);
+        let bytes = builder.build().unwrap();
+        let rebuilt = PortableExecutable::try_from(bytes.clone()).unwrap();
+        rebuilt.verify_checksum().unwrap();
+
+        let old_end = pe.nt_headers.opt_header.win_specific_fields.size_of_image;
+        let section = rebuilt
+            .section_table
+            .get_section_header(".solaire")
+            .unwrap();
+        assert_eq!(section.virtual_address, old_end);
+        assert_eq!(section.virtual_size, 0x1234);
+        assert_eq!(&section.raw_data[..0x1234], stub.as_slice());
+        assert_eq!(section.ptr_to_raw_data % 0x200, 0);
+        let imports = rebuilt.section_table.get_section_header(".idata2").unwrap();
+        assert_eq!(
+            rebuilt
+                .nt_headers
+                .opt_header
+                .win_specific_fields
+                .size_of_image,
+            imports.virtual_address + 0x1000
+        );
+
+        //  the symbol table MinGW left in the overlay moved with it
+        let ptr_to_symbol_table =
+            |pe: &PortableExecutable| pe.nt_headers.file_header.ptr_to_symbol_table;
+        assert!(ptr_to_symbol_table(&rebuilt) > ptr_to_symbol_table(&pe));
+        assert!(rebuilt.symbol_table().unwrap().find("main").is_some());
+
+        let table = rebuilt.get_import_table().unwrap();
+        let old_count = pe.get_import_table().unwrap().image_descriptors.len();
+        assert_eq!(table.image_descriptors.len(), old_count + 1);
+        assert_eq!(table.image_descriptors[0].name, "KERNEL32.dll");
+        let solaire = table.image_descriptors.last().unwrap();
+        assert_eq!(solaire.name, "solaire.dll");
+        let entries = &solaire.import_lookup_table.entries;
+        assert_eq!(entries[1].name().as_deref(), Some("unload"));
+        let FuncAddress::X64(iat) = entries[0].func_ptr_address() else {
+            panic!("expected an x64 IAT slot");
+        };
+        assert!(*iat >= imports.virtual_address as u64);
+    }
+
+    #[test]
+    fn resizes_and_removes_sections() {
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        let mut builder = pe.builder().unwrap();
+        //  .text can't grow past .rdata
+        assert!(builder.resize_section(".text", 0x100000).is_err());
+        let last = builder.sections.last().unwrap().header.name().to_string();
+        assert!(builder.resize_section(&last, u32::MAX).is_err());
+        builder.resize_section(&last, 0x3000).unwrap();
+        //  the debug and load config directories point into .rdata
+        assert!(builder.remove_section(".rdata").is_err());
+        assert!(builder.remove_section(".reloc").is_err());
+        let removed = ".data".to_string();
+        builder.remove_section(&removed).unwrap();
+        assert!(builder.remove_section(&removed).is_err());
+        assert!(builder.remove_section(".textbss").is_err());
+
+        let bytes = builder.build().unwrap();
+        let rebuilt = PortableExecutable::try_from(bytes).unwrap();
+        let headers = &rebuilt.section_table.section_headers;
+        assert_eq!(headers.len(), pe.section_table.section_headers.len() - 1);
+        assert!(rebuilt.section_table.get_section_header(&removed).is_none());
+        //  no gap in memory
+        for pair in headers.windows(2) {
+            assert_eq!(
+                (pair[0].virtual_address + pair[0].virtual_size).div_ceil(0x1000) * 0x1000,
+                pair[1].virtual_address
+            );
+        }
+        assert_eq!(headers.last().unwrap().virtual_size, 0x3000);
+    }
+
+    #[test]
+    fn headers_cant_overlap_the_first_section() {
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        let mut builder = pe.builder().unwrap();
+        let first_section = pe.section_table.section_headers[0].virtual_address;
+        let mut added = 0;
+        let error = loop {
+            builder
+                .add_section(
+                    &format!(".s{}", added),
+                    vec![0; 0x10],
+                    SectionFlags::IMAGE_SCN_CNT_INITIALIZED_DATA,
+                )
+                .unwrap();
+            added += 1;
+            match builder.build() {
+                Ok(bytes) => {
+                    let size_of_headers = PortableExecutable::try_from(bytes)
+                        .unwrap()
+                        .nt_headers
+                        .opt_header
+                        .win_specific_fields
+                        .size_of_headers;
+                    assert!(size_of_headers <= first_section);
+                }
+                Err(error) => break error,
+            }
+        };
+        assert!(added > 1);
+        assert!(error.to_string().contains("first section"));
+    }
+
+    /// The x86 sample as a dumper sees it, mapped at 0x10000000 with its imports resolved to
+    /// 0x77000000 + 0x10 * their index
+    fn dumped_image(pe: &PortableExecutable) -> (Vec<(String, String)>, Vec<u8>) {
+        let imports = pe.get_import_table().unwrap();
+        let names = imports
+            .image_descriptors
+            .iter()
+            .flat_map(|descriptor| {
+                descriptor
+                    .import_lookup_table
+                    .entries
+                    .iter()
+                    .map(|entry| (descriptor.name.clone(), entry.name().unwrap()))
+            })
+            .collect::<Vec<(String, String)>>();
+        //  the loader writes the base it picked in the headers
+        let mut image = pe
+            .map_image(
+                MapOptions::default()
+                    .image_base(0x10000000)
+                    .resolver(|dll, entry| {
+                        let import = (dll.to_string(), entry.name().unwrap());
+                        let idx = names.iter().position(|name| *name == import).unwrap();
+                        Some(0x77000000 + idx as u64 * 0x10)
+                    }),
+            )
+            .unwrap();
+        //  a packer wiping the import directory
+        let import_dir = pe.get_image_directory(ImageDirectoryEntry::IMPORT);
+        let start = import_dir.virtual_address as usize;
+        image[start..start + import_dir.size as usize].fill(0);
+        (names, image)
+    }
+
+    #[test]
+    fn rebuilds_a_dumped_image() {
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        let imports = pe.get_import_table().unwrap();
+        let (names, image) = dumped_image(&pe);
+
+        let mut builder = PeBuilder::from_image(&image).unwrap();
+        let iat = pe.get_image_directory(ImageDirectoryEntry::IAT);
+        builder
+            .rebuild_imports(iat.virtual_address, iat.size, |address| {
+                let (dll, name) = names.get((address as usize - 0x77000000) / 0x10)?;
+                Some((dll.clone(), ImportSymbol::Name(name.clone())))
+            })
+            .unwrap();
+        assert!(builder.rebuild_imports(0xfffffff0, 0x20, |_| None).is_err());
+        let rebuilt = PortableExecutable::try_from(builder.build().unwrap()).unwrap();
+        rebuilt.verify_checksum().unwrap();
+        assert_eq!(
+            rebuilt
+                .nt_headers
+                .opt_header
+                .win_specific_fields
+                .image_base
+                .value(),
+            0x10000000
+        );
+
+        let rebuilt_imports = rebuilt.get_import_table().unwrap();
+        assert_eq!(
+            rebuilt_imports.image_descriptors.len(),
+            imports.image_descriptors.len()
+        );
+        //  the descriptors come out in IAT order
+        for new in &rebuilt_imports.image_descriptors {
+            let old = imports
+                .image_descriptors
+                .iter()
+                .find(|old| old.first_thunk == new.first_thunk)
+                .unwrap();
+            assert_eq!(old.name, new.name);
+            let old_names = old.import_lookup_table.entries.iter().map(|e| e.name());
+            let new_names = new.import_lookup_table.entries.iter().map(|e| e.name());
+            assert!(old_names.eq(new_names));
+        }
+        //  the code is the relocated code, and reloads the same way
+        let rebased = pe
+            .map_image(MapOptions::default().image_base(0x10000000))
+            .unwrap();
+        let remapped = rebuilt.map_image(MapOptions::default()).unwrap();
+        let text = pe.section_table.get_section_header(".text").unwrap();
+        let text =
+            text.virtual_address as usize..(text.virtual_address + text.virtual_size) as usize;
+        assert_eq!(rebased[text.clone()], remapped[text]);
+    }
+
+    #[test]
+    fn rebuilds_an_iat_at_the_end_of_its_section() {
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        let (names, mut image) = dumped_image(&pe);
+        //  nothing but zeros past the last IAT entry, so its terminator gets trimmed
+        let iat = pe.get_image_directory(ImageDirectoryEntry::IAT);
+        let idata = pe.section_table.get_section_header(".idata").unwrap();
+        let iat_end = (iat.virtual_address + iat.size) as usize;
+        image[iat_end - 4..(idata.virtual_address + idata.virtual_size) as usize].fill(0);
+
+        let mut builder = PeBuilder::from_image(&image).unwrap();
+        builder
+            .rebuild_imports(iat.virtual_address, iat.size, |address| {
+                let (dll, name) = names.get((address as usize - 0x77000000) / 0x10)?;
+                Some((dll.clone(), ImportSymbol::Name(name.clone())))
+            })
+            .unwrap();
+        let rebuilt = PortableExecutable::try_from(builder.build().unwrap()).unwrap();
+        let rebuilt_imports = rebuilt.get_import_table().unwrap();
+        assert_eq!(
+            rebuilt_imports.image_descriptors.len(),
+            pe.get_import_table().unwrap().image_descriptors.len()
+        );
+    }
+}
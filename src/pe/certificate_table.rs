@@ -0,0 +1,650 @@
+use sha2::Digest;
+
+use super::{cursor::Cursor, optional_header::ImageDataDirectory, PeError};
+
+const WIN_CERT_TYPE_X509: u16 = 1;
+const WIN_CERT_TYPE_PKCS_SIGNED_DATA: u16 = 2;
+const WIN_CERT_TYPE_TS_STACK_SIGNED: u16 = 4;
+
+const OID_SIGNED_DATA: &str = "1.2.840.113549.1.7.2";
+const OID_SPC_INDIRECT_DATA: &str = "1.3.6.1.4.1.311.2.1.4";
+
+const DER_INTEGER: u8 = 0x02;
+const DER_OCTET_STRING: u8 = 0x04;
+const DER_OID: u8 = 0x06;
+const DER_SEQUENCE: u8 = 0x30;
+const DER_SET: u8 = 0x31;
+const DER_CONTEXT_0: u8 = 0xa0;
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#the-attribute-certificate-table-image-only
+/// Unlike every other directory the address is a file offset, the table isn't mapped
+pub fn get_certificate_table(
+    bytes: &[u8],
+    certificate_dir: ImageDataDirectory,
+) -> Result<CertificateTable, PeError> {
+    if certificate_dir.virtual_address == 0 || certificate_dir.size == 0 {
+        return Err(PeError::MissingTable(
+            "The executable doesn't have a certificate table".to_string(),
+        ));
+    }
+    let mut cursor = Cursor::from_slice(
+        bytes,
+        certificate_dir.virtual_address as usize,
+        certificate_dir.size as usize,
+    )?;
+    let mut certificates = vec![];
+    while cursor.position + 8 <= cursor.bytes.len() {
+        let offset = cursor.offset();
+        let length = cursor.read_u32()?;
+        let revision = cursor.read_u16()?;
+        let certificate_type = CertificateType::from(cursor.read_u16()?);
+        let data =
+            cursor
+                .read_slice((length as usize).checked_sub(8).ok_or(PeError::ParseError(
+                    format!(
+                        "Invalid WIN_CERTIFICATE length at {:#x}: {}",
+                        offset, length
+                    ),
+                ))?)?
+                .to_vec();
+        //  entries are quadword aligned
+        cursor.position = cursor.position.next_multiple_of(8);
+        certificates.push(WinCertificate {
+            offset,
+            revision,
+            certificate_type,
+            data,
+        });
+    }
+    Ok(CertificateTable { certificates })
+}
+
+#[derive(Debug, Clone)]
+pub struct CertificateTable {
+    pub certificates: Vec<WinCertificate>,
+}
+
+impl CertificateTable {
+    /// The first Authenticode signature, nested signatures live in its unsigned attributes
+    pub fn signature(&self) -> Result<SignedData, PeError> {
+        self.certificates
+            .iter()
+            .find(|cert| cert.certificate_type == CertificateType::PkcsSignedData)
+            .ok_or(PeError::MissingTable(
+                "The certificate table doesn't have a PKCS#7 signature".to_string(),
+            ))?
+            .signed_data()
+    }
+}
+
+/// Aka WIN_CERTIFICATE
+#[derive(Debug, Clone)]
+pub struct WinCertificate {
+    /// File offset of the entry
+    pub offset: usize,
+    pub revision: u16,
+    pub certificate_type: CertificateType,
+    /// bCertificate, without the header and the alignment padding
+    pub data: Vec<u8>,
+}
+
+impl WinCertificate {
+    pub fn signed_data(&self) -> Result<SignedData, PeError> {
+        parse_signed_data(&self.data)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CertificateType {
+    X509,
+    PkcsSignedData,
+    TsStackSigned,
+    Other(u16),
+}
+
+impl From<u16> for CertificateType {
+    fn from(value: u16) -> Self {
+        match value {
+            WIN_CERT_TYPE_X509 => CertificateType::X509,
+            WIN_CERT_TYPE_PKCS_SIGNED_DATA => CertificateType::PkcsSignedData,
+            WIN_CERT_TYPE_TS_STACK_SIGNED => CertificateType::TsStackSigned,
+            _ => CertificateType::Other(value),
+        }
+    }
+}
+
+/// The parts of the PKCS#7 SignedData needed to tell who signed the image and what they signed.
+/// Only DER is read, signatures using BER indefinite lengths fail to parse
+#[derive(Debug, Clone)]
+pub struct SignedData {
+    /// The algorithm of the image hash in SpcIndirectDataContent
+    pub digest_algorithm: DigestAlgorithm,
+    /// The signed image hash, compare it with [`authenticode_hash`]
+    pub digest: Vec<u8>,
+    pub signer: Signer,
+    pub certificates: Vec<Certificate>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Signer {
+    /// Only known if the signer's certificate is embedded, which it always is for signtool
+    pub subject: Option<String>,
+    pub issuer: String,
+    /// Big endian, as encoded
+    pub serial: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Certificate {
+    pub subject: String,
+    pub issuer: String,
+    pub serial: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DigestAlgorithm {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+    /// The dotted OID
+    Other(String),
+}
+
+impl From<&str> for DigestAlgorithm {
+    fn from(oid: &str) -> Self {
+        match oid {
+            "1.3.14.3.2.26" => DigestAlgorithm::Sha1,
+            "2.16.840.1.101.3.4.2.1" => DigestAlgorithm::Sha256,
+            "2.16.840.1.101.3.4.2.2" => DigestAlgorithm::Sha384,
+            "2.16.840.1.101.3.4.2.3" => DigestAlgorithm::Sha512,
+            _ => DigestAlgorithm::Other(oid.to_string()),
+        }
+    }
+}
+
+/// The Authenticode image hash: the whole file except the CheckSum field,
+/// the certificate table directory entry and the certificate table itself.
+/// The file is hashed in order, which matches signtool for images whose sections are contiguous
+pub fn authenticode_hash(
+    bytes: &[u8],
+    checksum_offset: usize,
+    certificate_dir_offset: usize,
+    certificate_dir: &ImageDataDirectory,
+    algorithm: &DigestAlgorithm,
+) -> Result<Vec<u8>, PeError> {
+    let table_start = match certificate_dir.size {
+        0 => bytes.len(),
+        _ => certificate_dir.virtual_address as usize,
+    };
+    let table_end = table_start + certificate_dir.size as usize;
+    if checksum_offset + 4 > certificate_dir_offset
+        || certificate_dir_offset + 8 > table_start
+        || table_end > bytes.len()
+    {
+        return Err(PeError::OutOfBounds {
+            offset: table_start,
+            size: certificate_dir.size as usize,
+        });
+    }
+    let ranges = [
+        &bytes[..checksum_offset],
+        &bytes[checksum_offset + 4..certificate_dir_offset],
+        &bytes[certificate_dir_offset + 8..table_start],
+        &bytes[table_end..],
+    ];
+    let hash = match algorithm {
+        DigestAlgorithm::Sha1 => hash_ranges::<sha1::Sha1>(&ranges),
+        DigestAlgorithm::Sha256 => hash_ranges::<sha2::Sha256>(&ranges),
+        DigestAlgorithm::Sha384 => hash_ranges::<sha2::Sha384>(&ranges),
+        DigestAlgorithm::Sha512 => hash_ranges::<sha2::Sha512>(&ranges),
+        DigestAlgorithm::Other(oid) => {
+            return Err(PeError::ParseError(format!(
+                "Unsupported digest algorithm: {}",
+                oid
+            )))
+        }
+    };
+    Ok(hash)
+}
+
+fn hash_ranges<D: Digest>(ranges: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = D::new();
+    for range in ranges {
+        hasher.update(range);
+    }
+    hasher.finalize().to_vec()
+}
+
+/// ContentInfo { signedData, [0] SignedData }, see RFC 2315 and the Authenticode spec
+fn parse_signed_data(bytes: &[u8]) -> Result<SignedData, PeError> {
+    let (content_info, _) = read_der(bytes)?;
+    let content_info = content_info.expect(DER_SEQUENCE)?.children()?;
+    let content_type = field(&content_info, 0, DER_OID)?.oid();
+    if content_type != OID_SIGNED_DATA {
+        return Err(PeError::ParseError(format!(
+            "Expected PKCS#7 SignedData, got {}",
+            content_type
+        )));
+    }
+    let signed_data = field(&content_info, 1, DER_CONTEXT_0)?.children()?;
+    let signed_data = field(&signed_data, 0, DER_SEQUENCE)?.children()?;
+
+    //  version, digestAlgorithms, contentInfo, [0] certificates, [1] crls, signerInfos
+    let encap_content = field(&signed_data, 2, DER_SEQUENCE)?.children()?;
+    let encap_type = field(&encap_content, 0, DER_OID)?.oid();
+    if encap_type != OID_SPC_INDIRECT_DATA {
+        return Err(PeError::ParseError(format!(
+            "Expected SpcIndirectDataContent, got {}",
+            encap_type
+        )));
+    }
+    let indirect_data = field(&encap_content, 1, DER_CONTEXT_0)?.children()?;
+    let indirect_data = field(&indirect_data, 0, DER_SEQUENCE)?.children()?;
+    let digest_info = field(&indirect_data, 1, DER_SEQUENCE)?.children()?;
+    let algorithm = field(&digest_info, 0, DER_SEQUENCE)?.children()?;
+    let digest_algorithm = DigestAlgorithm::from(field(&algorithm, 0, DER_OID)?.oid().as_str());
+    let digest = field(&digest_info, 1, DER_OCTET_STRING)?.content.to_vec();
+
+    let certificates = match signed_data.get(3) {
+        Some(certificates) if certificates.tag == DER_CONTEXT_0 => certificates
+            .children()?
+            .iter()
+            .map(parse_certificate)
+            .collect::<Result<Vec<Certificate>, PeError>>()?,
+        _ => vec![],
+    };
+
+    let signer_infos = signed_data
+        .last()
+        .ok_or(malformed("SignedData"))?
+        .expect(DER_SET)?
+        .children()?;
+    let signer_info = field(&signer_infos, 0, DER_SEQUENCE)?.children()?;
+    let issuer_and_serial = field(&signer_info, 1, DER_SEQUENCE)?.children()?;
+    let issuer = parse_name(field(&issuer_and_serial, 0, DER_SEQUENCE)?)?;
+    let serial = field(&issuer_and_serial, 1, DER_INTEGER)?.content.to_vec();
+    let subject = certificates
+        .iter()
+        .find(|cert| cert.issuer == issuer && cert.serial == serial)
+        .map(|cert| cert.subject.clone());
+
+    Ok(SignedData {
+        digest_algorithm,
+        digest,
+        signer: Signer {
+            subject,
+            issuer,
+            serial,
+        },
+        certificates,
+    })
+}
+
+/// Certificate { TBSCertificate, signatureAlgorithm, signature }, see RFC 5280
+fn parse_certificate(certificate: &Der) -> Result<Certificate, PeError> {
+    let certificate = certificate.expect(DER_SEQUENCE)?.children()?;
+    let mut tbs = field(&certificate, 0, DER_SEQUENCE)?.children()?;
+    //  the version is optional and defaults to v1
+    if tbs.first().map(|der| der.tag) == Some(DER_CONTEXT_0) {
+        tbs.remove(0);
+    }
+    //  serialNumber, signature, issuer, validity, subject
+    Ok(Certificate {
+        serial: field(&tbs, 0, DER_INTEGER)?.content.to_vec(),
+        issuer: parse_name(field(&tbs, 2, DER_SEQUENCE)?)?,
+        subject: parse_name(field(&tbs, 4, DER_SEQUENCE)?)?,
+    })
+}
+
+/// Renders an X.500 Name like "CN=Solaire, O=Astora, C=US"
+fn parse_name(name: &Der) -> Result<String, PeError> {
+    let mut attributes = vec![];
+    for rdn in name.children()? {
+        for attribute in rdn.expect(DER_SET)?.children()? {
+            let attribute = attribute.expect(DER_SEQUENCE)?.children()?;
+            let oid = field(&attribute, 0, DER_OID)?.oid();
+            let value = attribute.get(1).ok_or(malformed("AttributeTypeAndValue"))?;
+            let key = match oid.as_str() {
+                "2.5.4.3" => "CN",
+                "2.5.4.6" => "C",
+                "2.5.4.7" => "L",
+                "2.5.4.8" => "ST",
+                "2.5.4.10" => "O",
+                "2.5.4.11" => "OU",
+                oid => oid,
+            };
+            attributes.push(format!("{}={}", key, value.string()));
+        }
+    }
+    Ok(attributes.join(", "))
+}
+
+fn malformed(what: &str) -> PeError {
+    PeError::ParseError(format!("Malformed {}", what))
+}
+
+fn field<'a, 'b>(fields: &'b [Der<'a>], idx: usize, tag: u8) -> Result<&'b Der<'a>, PeError> {
+    fields
+        .get(idx)
+        .ok_or(malformed("PKCS#7 structure"))?
+        .expect(tag)
+}
+
+/// A DER tag, length, value triple
+struct Der<'a> {
+    tag: u8,
+    content: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    fn expect(&self, tag: u8) -> Result<&Self, PeError> {
+        if self.tag != tag {
+            return Err(PeError::ParseError(format!(
+                "Expected DER tag {:#x}, got {:#x}",
+                tag, self.tag
+            )));
+        }
+        Ok(self)
+    }
+
+    fn children(&self) -> Result<Vec<Der<'a>>, PeError> {
+        let mut children = vec![];
+        let mut rest = self.content;
+        while !rest.is_empty() {
+            let (child, next) = read_der(rest)?;
+            children.push(child);
+            rest = next;
+        }
+        Ok(children)
+    }
+
+    fn oid(&self) -> String {
+        let Some((first, rest)) = self.content.split_first() else {
+            return String::new();
+        };
+        //  the first byte packs the first two arcs, the first one is at most 2
+        let first_arc = (first / 40).min(2);
+        let mut arcs = vec![first_arc as u64, (first - first_arc * 40) as u64];
+        let mut arc = 0u64;
+        for byte in rest {
+            arc = arc << 7 | (byte & 0x7f) as u64;
+            if byte & 0x80 == 0 {
+                arcs.push(arc);
+                arc = 0;
+            }
+        }
+        arcs.iter()
+            .map(|arc| arc.to_string())
+            .collect::<Vec<String>>()
+            .join(".")
+    }
+
+    fn string(&self) -> String {
+        match self.tag {
+            //  BMPString is UTF-16BE
+            0x1e => String::from_utf16_lossy(
+                &self
+                    .content
+                    .chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect::<Vec<u16>>(),
+            ),
+            _ => String::from_utf8_lossy(self.content).into_owned(),
+        }
+    }
+}
+
+/// Definite lengths only: Authenticode signatures are DER, so the BER indefinite length form
+/// (0x80, terminated by an end-of-contents marker) is rejected as malformed
+fn read_der(bytes: &[u8]) -> Result<(Der<'_>, &[u8]), PeError> {
+    let mut cursor = Cursor::new(bytes);
+    let tag = cursor.read_u8()?;
+    let length = match cursor.read_u8()? {
+        length @ 0..=0x7f => length as usize,
+        long @ 0x81..=0x84 => {
+            let mut length = 0usize;
+            for byte in cursor.read_slice((long & 0x7f) as usize)? {
+                length = length << 8 | *byte as usize;
+            }
+            length
+        }
+        _ => return Err(malformed("DER length")),
+    };
+    let content = cursor.read_slice(length)?;
+    Ok((Der { tag, content }, &bytes[cursor.position..]))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        pe::{checksum::CHECKSUM_OFFSET, PeError, PortableExecutable},
+        util::to_hex,
+    };
+
+    use super::{parse_signed_data, read_der, CertificateType, DigestAlgorithm};
+
+    fn der(tag: u8, parts: &[&[u8]]) -> Vec<u8> {
+        let content = parts.concat();
+        let mut out = vec![tag];
+        match content.len() {
+            len @ 0..=0x7f => out.push(len as u8),
+            len => {
+                out.push(0x82);
+                out.extend((len as u16).to_be_bytes());
+            }
+        }
+        out.extend(content);
+        out
+    }
+
+    fn name(common_name: &str) -> Vec<u8> {
+        let attribute = der(
+            0x30,
+            &[
+                &der(0x06, &[&[0x55, 0x04, 0x03]]),
+                &der(0x0c, &[common_name.as_bytes()]),
+            ],
+        );
+        let country = der(
+            0x30,
+            &[&der(0x06, &[&[0x55, 0x04, 0x06]]), &der(0x13, &[b"US"])],
+        );
+        der(0x30, &[&der(0x31, &[&attribute]), &der(0x31, &[&country])])
+    }
+
+    /// A structurally valid signtool-like signature, the cryptographic signature is left empty
+    fn signed_data(digest: &[u8]) -> Vec<u8> {
+        let null = der(0x05, &[]);
+        let sha256 = der(
+            0x30,
+            &[
+                &der(
+                    0x06,
+                    &[&[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01]],
+                ),
+                &null,
+            ],
+        );
+        let spc_indirect_data = der(
+            0x06,
+            &[&[0x2b, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x02, 0x01, 0x04]],
+        );
+        let indirect_data = der(
+            0x30,
+            &[
+                &der(
+                    0x30,
+                    &[&der(
+                        0x06,
+                        &[&[0x2b, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x02, 0x01, 0x0f]],
+                    )],
+                ),
+                &der(0x30, &[&sha256, &der(0x04, &[digest])]),
+            ],
+        );
+        let serial = der(0x02, &[&[0x01, 0x23, 0x45]]);
+        let tbs = der(
+            0x30,
+            &[
+                &der(0xa0, &[&der(0x02, &[&[2]])]),
+                &serial,
+                &sha256,
+                &name("Solaire Root CA"),
+                &der(0x30, &[]),
+                &name("Solaire of Astora"),
+            ],
+        );
+        let certificate = der(0x30, &[&tbs, &sha256, &der(0x03, &[&[0]])]);
+        let signer_info = der(
+            0x30,
+            &[
+                &der(0x02, &[&[1]]),
+                &der(0x30, &[&name("Solaire Root CA"), &serial]),
+                &sha256,
+                &sha256,
+                &der(0x04, &[]),
+            ],
+        );
+        let signed_data = der(
+            0x30,
+            &[
+                &der(0x02, &[&[1]]),
+                &der(0x31, &[&sha256]),
+                &der(0x30, &[&spc_indirect_data, &der(0xa0, &[&indirect_data])]),
+                &der(0xa0, &[&certificate]),
+                &der(0x31, &[&signer_info]),
+            ],
+        );
+        der(
+            0x30,
+            &[
+                &der(
+                    0x06,
+                    &[&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02]],
+                ),
+                &der(0xa0, &[&signed_data]),
+            ],
+        )
+    }
+
+    /// Appends a WIN_CERTIFICATE like signtool does: 8 byte aligned, at the end of the file.
+    /// Signs the real image hash unless `digest` is given
+    fn sign(mut bytes: Vec<u8>, digest: Option<&[u8]>) -> Vec<u8> {
+        bytes.resize(bytes.len().next_multiple_of(8), 0);
+        //  the directory entry isn't part of the hash, it can be filled in afterwards
+        let pe = PortableExecutable::try_from(bytes.clone()).unwrap();
+        let digest = match digest {
+            Some(digest) => digest.to_vec(),
+            None => pe.authenticode_hash(&DigestAlgorithm::Sha256).unwrap(),
+        };
+        let certificate = signed_data(&digest);
+        let table_offset = bytes.len();
+        let padded_len = (certificate.len() + 8).next_multiple_of(8);
+        let dir_offset = pe.dos_header.e_lfanew as usize + 24 + 112 + 4 * 8;
+        bytes[dir_offset..dir_offset + 4].copy_from_slice(&(table_offset as u32).to_le_bytes());
+        bytes[dir_offset + 4..dir_offset + 8].copy_from_slice(&(padded_len as u32).to_le_bytes());
+
+        bytes.extend(((certificate.len() + 8) as u32).to_le_bytes());
+        bytes.extend(0x0200u16.to_le_bytes());
+        bytes.extend(2u16.to_le_bytes());
+        bytes.extend(certificate);
+        bytes.resize(table_offset + padded_len, 0);
+        bytes
+    }
+
+    #[test]
+    fn parses_authenticode_signatures() {
+        let bytes = std::fs::read("sample_executable.exe").unwrap();
+        let pe = PortableExecutable::try_from(bytes.clone()).unwrap();
+        assert!(matches!(
+            pe.certificate_table(),
+            Err(PeError::MissingTable(_))
+        ));
+
+        let signed = PortableExecutable::try_from(sign(bytes.clone(), None)).unwrap();
+        let table = signed.certificate_table().unwrap();
+        assert_eq!(table.certificates.len(), 1);
+        let certificate = &table.certificates[0];
+        assert_eq!(certificate.revision, 0x0200);
+        assert_eq!(
+            certificate.certificate_type,
+            CertificateType::PkcsSignedData
+        );
+
+        let signature = table.signature().unwrap();
+        assert_eq!(signature.digest_algorithm, DigestAlgorithm::Sha256);
+        assert_eq!(signature.signer.serial, [0x01, 0x23, 0x45]);
+        assert_eq!(signature.signer.issuer, "CN=Solaire Root CA, C=US");
+        assert_eq!(
+            signature.signer.subject.as_deref(),
+            Some("CN=Solaire of Astora, C=US")
+        );
+        assert!(signed.verify_authenticode().unwrap());
+
+        //  the hash doesn't depend on the signature or the checksum
+        assert_eq!(
+            pe.authenticode_hash(&DigestAlgorithm::Sha256).unwrap(),
+            signature.digest
+        );
+
+        let tampered = PortableExecutable::try_from(sign(bytes, Some(&[0xaa; 32]))).unwrap();
+        assert!(!tampered.verify_authenticode().unwrap());
+    }
+
+    #[test]
+    fn matches_a_real_signature() {
+        //  conda's cli-32.exe launcher, signed by Anaconda with signtool
+        let bytes = std::fs::read("sample_signed_x86.exe").unwrap();
+        let pe = PortableExecutable::try_from(bytes.clone()).unwrap();
+        let signature = pe.certificate_table().unwrap().signature().unwrap();
+        assert_eq!(signature.digest_algorithm, DigestAlgorithm::Sha256);
+        //  the messageDigest embedded by signtool, not something this crate computed
+        assert_eq!(
+            to_hex(&signature.digest),
+            "8f2fbb21027ce84b23b282956f16e3b8529e5c411b7e83bbfe69e9c05a778873"
+        );
+        assert_eq!(
+            to_hex(&pe.authenticode_hash(&DigestAlgorithm::Sha256).unwrap()),
+            to_hex(&signature.digest)
+        );
+        assert!(pe.verify_authenticode().unwrap());
+        assert_eq!(
+            signature.signer.issuer,
+            "C=US, O=DigiCert, Inc., CN=DigiCert Trusted G4 Code Signing RSA4096 SHA384 2021 CA1"
+        );
+        assert!(signature
+            .signer
+            .subject
+            .unwrap()
+            .ends_with("O=Anaconda, Inc., CN=Anaconda, Inc."));
+
+        //  the checksum isn't hashed, the code is
+        let mut patched = bytes.clone();
+        let checksum_offset = pe.dos_header.e_lfanew as usize + 24 + CHECKSUM_OFFSET;
+        patched[checksum_offset] ^= 0xff;
+        assert!(PortableExecutable::try_from(patched.clone())
+            .unwrap()
+            .verify_authenticode()
+            .unwrap());
+        let text = pe.section_table.get_section_header(".text").unwrap();
+        patched[text.ptr_to_raw_data as usize] ^= 0xff;
+        assert!(!PortableExecutable::try_from(patched)
+            .unwrap()
+            .verify_authenticode()
+            .unwrap());
+    }
+
+    #[test]
+    fn rejects_indefinite_lengths() {
+        assert!(read_der(&[0x30, 0x80, 0x05, 0x00, 0x00, 0x00]).is_err());
+        let signature = sign(std::fs::read("sample_executable.exe").unwrap(), None);
+        let pe = PortableExecutable::try_from(signature).unwrap();
+        let mut certificate = pe.certificate_table().unwrap().certificates[0].data.clone();
+        certificate[1] = 0x80;
+        assert!(matches!(
+            parse_signed_data(&certificate),
+            Err(PeError::ParseError(_))
+        ));
+    }
+}
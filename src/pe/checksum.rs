@@ -0,0 +1,56 @@
+/// Offset of CheckSum from the start of the optional header, the same for PE32 and PE32+
+pub(super) const CHECKSUM_OFFSET: usize = 64;
+
+/// The algorithm of imagehlp's CheckSumMappedFile: a 16 bit one's complement sum of the
+/// file with the CheckSum field skipped, plus the file length
+pub(super) fn pe_checksum(bytes: &[u8], checksum_offset: usize) -> u32 {
+    let mut sum: u64 = 0;
+    for (idx, word) in bytes.chunks(2).enumerate() {
+        let offset = idx * 2;
+        if (checksum_offset..checksum_offset + 4).contains(&offset) {
+            continue;
+        }
+        //  an odd trailing byte is padded with a zero
+        let word = match word {
+            [low, high] => u16::from_le_bytes([*low, *high]),
+            [low] => *low as u16,
+            _ => unreachable!(),
+        };
+        sum += word as u64;
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum = (sum & 0xffff) + (sum >> 16);
+    (sum as u32).wrapping_add(bytes.len() as u32)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pe::{PeError, PortableExecutable};
+
+    #[test]
+    fn verifies_checksums() {
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        assert_eq!(pe.compute_checksum(), 0x170f7);
+        assert!(pe.verify_checksum().is_ok());
+
+        //  the checksum field itself doesn't count, anything else does
+        let mut bytes = std::fs::read("sample_executable.exe").unwrap();
+        let checksum_offset = pe.dos_header.e_lfanew as usize + 24 + super::CHECKSUM_OFFSET;
+        bytes[checksum_offset] ^= 0xff;
+        let patched = PortableExecutable::try_from(bytes.clone()).unwrap();
+        assert_eq!(patched.compute_checksum(), 0x170f7);
+        bytes[0x400] ^= 0xff;
+        let patched = PortableExecutable::try_from(bytes).unwrap();
+        assert!(matches!(
+            patched.verify_checksum(),
+            Err(PeError::ChecksumMismatch { .. })
+        ));
+
+        //  MSVC only sets it with /RELEASE
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        assert!(matches!(
+            pe.verify_checksum(),
+            Err(PeError::ChecksumMismatch { stored: 0, .. })
+        ));
+    }
+}
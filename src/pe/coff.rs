@@ -0,0 +1,263 @@
+use super::{
+    cursor::{slice_at, u16_at, Cursor},
+    file_header::{parse_file_header, FileHeader},
+    section_table::{
+        parse_section_headers, CoffRelocation, SectionHeader, SectionNumber, SectionTable,
+        StandardSymbolRecord,
+    },
+    symbol_table::SymbolTable,
+    PeError,
+};
+
+/// A bare COFF object as written by cl /c or an assembler: the file header comes first,
+/// there's no DOS stub, PE signature or optional header
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#coff-file-header-object-and-image
+#[derive(Clone)]
+pub struct CoffObject {
+    pub file_header: FileHeader,
+    pub section_table: SectionTable,
+    pub symbol_table: SymbolTable,
+    bytes: Vec<u8>,
+}
+
+impl std::fmt::Debug for CoffObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CoffObject")
+            .field("file_header", &self.file_header)
+            .field("section_table", &self.section_table)
+            .field("bytes", &self.bytes.len())
+            .finish()
+    }
+}
+
+impl CoffObject {
+    pub fn from_file(path: impl Into<String>) -> Result<CoffObject, PeError> {
+        let file = std::fs::read(path.into())?;
+        CoffObject::try_from(file)
+    }
+
+    fn parse(bytes: impl Into<Vec<u8>>) -> Result<Self, PeError> {
+        let bytes = bytes.into();
+        if bytes.starts_with(b"MZ") {
+            return Err(PeError::ParseError(
+                "This is an image, not an object, parse it with PortableExecutable".to_string(),
+            ));
+        }
+        //  import objects and /bigobj files start with IMAGE_FILE_MACHINE_UNKNOWN and 0xffff
+        if u16_at(&bytes, 0)? == 0 && u16_at(&bytes, 2)? == 0xffff {
+            return Err(PeError::ParseError(
+                "Import objects and big objects aren't plain COFF objects".to_string(),
+            ));
+        }
+
+        let mut cursor = Cursor::new(&bytes);
+        let file_header = parse_file_header(&mut cursor)?;
+        //  objects shouldn't have one, skip it if the compiler wrote one anyway
+        cursor.skip(file_header.size_of_optional_header as usize);
+        let mut section_table = parse_section_headers(&mut cursor, file_header.number_of_sections)?;
+        for section in &mut section_table.section_headers {
+            //  uninitialized data has a size but no file data
+            if section.ptr_to_raw_data == 0 {
+                continue;
+            }
+            section.raw_data = slice_at(
+                &bytes,
+                section.ptr_to_raw_data as usize,
+                section.size_of_raw_data as usize,
+            )?
+            .to_vec();
+        }
+
+        let symbol_table = match file_header.symbol_table(&bytes) {
+            Ok(symbol_table) => symbol_table,
+            Err(PeError::MissingTable(_)) => SymbolTable::default(),
+            Err(e) => return Err(e),
+        };
+        Ok(CoffObject {
+            file_header,
+            section_table,
+            symbol_table,
+            bytes,
+        })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The section's name, names longer than 8 bytes are read from the string table
+    pub fn section_name(&self, section: &SectionHeader) -> Result<String, PeError> {
+        self.symbol_table.string_table.section_name(&section.name)
+    }
+
+    pub fn get_section(&self, name: &str) -> Option<&SectionHeader> {
+        self.section_table
+            .section_headers
+            .iter()
+            .find(|section| self.section_name(section).is_ok_and(|n| n == name))
+    }
+
+    /// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#coff-relocations-object-only
+    pub fn relocations(&self, section: &SectionHeader) -> Result<Vec<CoffRelocation>, PeError> {
+        section.coff_relocations(&self.bytes, &self.file_header.machine)
+    }
+
+    /// The section a symbol is defined in, `None` for undefined, absolute and debug symbols
+    pub fn section_of(&self, symbol: &StandardSymbolRecord) -> Option<&SectionHeader> {
+        match symbol.section_number {
+            SectionNumber::Index(number) => {
+                self.section_table.section_headers.get(number as usize - 1)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<Vec<u8>> for CoffObject {
+    type Error = PeError;
+    fn try_from(data: Vec<u8>) -> Result<Self, PeError> {
+        Self::parse(data)
+    }
+}
+
+impl TryFrom<&[u8]> for CoffObject {
+    type Error = PeError;
+    fn try_from(data: &[u8]) -> Result<Self, PeError> {
+        Self::parse(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pe::{
+        file_header::Machine,
+        section_table::{AuxiliarySymbolRecord, RelocationType, SectionNumber, StorageClass},
+    };
+
+    use super::CoffObject;
+
+    #[test]
+    fn parses_an_amd64_object() {
+        let obj = CoffObject::from_file("sample_object.obj").unwrap();
+        assert_eq!(obj.file_header.machine, Machine::IMAGE_FILE_MACHINE_AMD64);
+        let names = obj
+            .section_table
+            .section_headers
+            .iter()
+            .map(|section| obj.section_name(section).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, [".text", ".data", ".bss", ".rdata"]);
+        let text = obj.get_section(".text").unwrap();
+        assert_eq!(text.raw_data.len(), 28);
+        assert!(obj.get_section(".bss").unwrap().raw_data.is_empty());
+
+        let symbols = &obj.symbol_table;
+        assert_eq!(symbols.records.len(), 18);
+        let relocations = obj.relocations(text).unwrap();
+        assert_eq!(relocations.len(), 3);
+        assert_eq!(relocations[1].virtual_address, 0xc);
+        assert_eq!(relocations[1].r#type, RelocationType::IMAGE_REL_AMD64_REL32);
+        //  a long name from the string table
+        let callee = symbols.get(relocations[1].symbol_table_index).unwrap();
+        assert_eq!(callee.name, "MessageBoxA_stub");
+        assert_eq!(callee.section_number, SectionNumber::IMAGE_SYM_UNDEFINED);
+
+        let (idx, entry) = symbols.find("shellcode_entry").unwrap();
+        assert_eq!(idx, 8);
+        assert!(entry.is_function_definition());
+        assert_eq!(obj.section_of(entry).unwrap().name(), ".text");
+
+        assert_eq!(
+            symbols.aux_records(0).collect::<Vec<_>>(),
+            [&AuxiliarySymbolRecord::SectionDefinitions {
+                length: 28,
+                number_of_relocations: 3,
+                number_of_linenumbers: 0,
+                checksum: 0xd43fe4de,
+                number: 1,
+                selection: 0,
+                unused: [0; 3],
+            }]
+        );
+        let (idx, hook) = symbols.find("optional_hook").unwrap();
+        assert_eq!(
+            hook.storage_class,
+            StorageClass::IMAGE_SYM_CLASS_WEAK_EXTERNAL
+        );
+        assert!(matches!(
+            symbols.aux_records(idx).next(),
+            Some(AuxiliarySymbolRecord::WeakExternals {
+                tag_index: 15,
+                characteristics: 3,
+                ..
+            })
+        ));
+        let (idx, _) = symbols.find(".file").unwrap();
+        let Some(AuxiliarySymbolRecord::Files { file_name }) = symbols.aux_records(idx).next()
+        else {
+            panic!("no file name record");
+        };
+        assert!(file_name.starts_with(b"stub.c\0"));
+    }
+
+    #[test]
+    fn parses_an_i386_object() {
+        let obj = CoffObject::from_file("sample_object_x86.obj").unwrap();
+        assert_eq!(obj.file_header.machine, Machine::IMAGE_FILE_MACHINE_I386);
+        let (_, entry) = obj.symbol_table.find("_shellcode_entry").unwrap();
+        assert!(entry.is_function_definition());
+        let text = obj.section_of(entry).unwrap();
+        assert_eq!(text.raw_data[0], 0x68);
+
+        let relocations = obj.relocations(text).unwrap();
+        let types = relocations
+            .iter()
+            .map(|relocation| (relocation.virtual_address, relocation.r#type.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                (0x1, RelocationType::IMAGE_REL_I386_DIR32),
+                (0x6, RelocationType::IMAGE_REL_I386_REL32),
+                (0xb, RelocationType::IMAGE_REL_I386_DIR32),
+            ]
+        );
+        let callee = obj.symbol_table.get(relocations[1].symbol_table_index);
+        assert_eq!(callee.unwrap().name, "_MessageBoxA_stub");
+
+        //  images aren't objects
+        let image = std::fs::read("sample_executable.exe").unwrap();
+        assert!(CoffObject::try_from(image).is_err());
+    }
+
+    #[test]
+    fn relocation_types_depend_on_the_machine() {
+        use Machine::*;
+        let from = RelocationType::from_machine;
+        assert_eq!(
+            from(&IMAGE_FILE_MACHINE_AMD64, 0x4).unwrap(),
+            RelocationType::IMAGE_REL_AMD64_REL32
+        );
+        assert_eq!(
+            from(&IMAGE_FILE_MACHINE_I386, 0x4).unwrap_err().to_string(),
+            "Parse Error: Invalid IMAGE_FILE_MACHINE_I386 relocation type: 0x4"
+        );
+        assert_eq!(
+            from(&IMAGE_FILE_MACHINE_ARMNT, 0x14).unwrap(),
+            RelocationType::IMAGE_REL_ARM_THUMB_BRANCH24
+        );
+        assert_eq!(
+            from(&IMAGE_FILE_MACHINE_ARM64, 0x3).unwrap(),
+            RelocationType::IMAGE_REL_ARM64_BRANCH26
+        );
+        assert_eq!(
+            from(&IMAGE_FILE_MACHINE_ARM64EC, 0x4).unwrap(),
+            RelocationType::IMAGE_REL_ARM64_PAGEBASE_REL21
+        );
+        assert_eq!(
+            Machine::try_from(0xa641).unwrap(),
+            IMAGE_FILE_MACHINE_ARM64EC
+        );
+        assert!(from(&IMAGE_FILE_MACHINE_IA64, 0).is_err());
+    }
+}
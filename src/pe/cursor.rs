@@ -1,49 +1,149 @@
 use crate::util::{i16_from_bytes, u16_from_bytes, u32_from_bytes, u64_from_bytes};
 
-pub struct Cursor {
-    pub bytes: Vec<u8>,
+use super::PeError;
+
+/// Reads little endian values out of a borrowed buffer
+pub struct Cursor<'a> {
+    pub bytes: &'a [u8],
     pub position: usize,
+    /// File offset of `bytes[0]`, so errors point into the file and not into the copy
+    base: usize,
 }
-impl Cursor {
-    pub fn new(bytes: Vec<u8>) -> Self {
-        Self { bytes, position: 0 }
+impl<'a> Cursor<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            position: 0,
+            base: 0,
+        }
+    }
+
+    /// A cursor over the `size` bytes at `offset`
+    pub fn from_slice(bytes: &'a [u8], offset: usize, size: usize) -> Result<Self, PeError> {
+        Ok(Self {
+            bytes: slice_at(bytes, offset, size)?,
+            position: 0,
+            base: offset,
+        })
+    }
+
+    /// The current position as an offset into the original data
+    pub fn offset(&self) -> usize {
+        self.base + self.position
+    }
+
+    pub fn read_str(&mut self, bytes: usize) -> Result<&'a str, PeError> {
+        let offset = self.offset();
+        std::str::from_utf8(self.read_slice(bytes)?).map_err(|_| PeError::InvalidString { offset })
     }
 
-    pub fn read_str(&mut self, bytes: usize) -> &str {
-        let result = std::str::from_utf8(&self.bytes[self.position..self.position + bytes]);
-        self.position += bytes;
-        result.unwrap()
+    pub fn read(&mut self, bytes: usize) -> Result<Vec<u8>, PeError> {
+        Ok(self.read_slice(bytes)?.to_vec())
     }
 
-    pub fn read(&mut self, bytes: usize) -> Vec<u8> {
-        let result = &self.bytes[self.position..self.position + bytes];
-        self.position += bytes;
-        result.to_vec()
+    pub fn read_slice(&mut self, bytes: usize) -> Result<&'a [u8], PeError> {
+        let start = self.position;
+        let end = start
+            .checked_add(bytes)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(PeError::OutOfBounds {
+                offset: self.offset(),
+                size: bytes,
+            })?;
+        self.position = end;
+        Ok(&self.bytes[start..end])
     }
 
     pub fn skip(&mut self, bytes: usize) {
-        self.position += bytes;
+        self.position = self.position.saturating_add(bytes);
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, PeError> {
+        Ok(self.read_slice(1)?[0])
     }
 
-    pub fn read_u8(&mut self) -> u8 {
-        let result = self.bytes[self.position];
-        self.position += 1;
-        result
+    pub fn read_u16(&mut self) -> Result<u16, PeError> {
+        Ok(u16_from_bytes(self.read_slice(2)?))
     }
 
-    pub fn read_u16(&mut self) -> u16 {
-        u16_from_bytes(&self.read(2))
+    pub fn read_i16(&mut self) -> Result<i16, PeError> {
+        Ok(i16_from_bytes(self.read_slice(2)?))
     }
 
-    pub fn read_i16(&mut self) -> i16 {
-        i16_from_bytes(&self.read(2))
+    pub fn read_u32(&mut self) -> Result<u32, PeError> {
+        Ok(u32_from_bytes(self.read_slice(4)?))
     }
 
-    pub fn read_u32(&mut self) -> u32 {
-        u32_from_bytes(&self.read(4))
+    pub fn read_u64(&mut self) -> Result<u64, PeError> {
+        Ok(u64_from_bytes(self.read_slice(8)?))
     }
+}
+
+/// Bounds checked `&bytes[offset..offset + size]`
+pub fn slice_at(bytes: &[u8], offset: usize, size: usize) -> Result<&[u8], PeError> {
+    offset
+        .checked_add(size)
+        .and_then(|end| bytes.get(offset..end))
+        .ok_or(PeError::OutOfBounds { offset, size })
+}
+
+pub fn u16_at(bytes: &[u8], offset: usize) -> Result<u16, PeError> {
+    Ok(u16_from_bytes(slice_at(bytes, offset, 2)?))
+}
+
+pub fn u32_at(bytes: &[u8], offset: usize) -> Result<u32, PeError> {
+    Ok(u32_from_bytes(slice_at(bytes, offset, 4)?))
+}
+
+pub fn u64_at(bytes: &[u8], offset: usize) -> Result<u64, PeError> {
+    Ok(u64_from_bytes(slice_at(bytes, offset, 8)?))
+}
+
+/// The bytes of the null terminated string at `offset`, without the terminator
+pub fn cstr_at(bytes: &[u8], offset: usize) -> Result<&[u8], PeError> {
+    let out_of_bounds = PeError::OutOfBounds { offset, size: 1 };
+    let rest = bytes.get(offset..).ok_or(out_of_bounds)?;
+    let len = rest
+        .iter()
+        .position(|byte| *byte == 0)
+        .ok_or(PeError::InvalidString { offset })?;
+    Ok(&rest[..len])
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pe::PeError;
+
+    use super::{cstr_at, u32_at, Cursor};
+
+    #[test]
+    fn reads_are_bounds_checked() {
+        let mut cursor = Cursor::from_slice(&[0u8; 0x20], 0x10, 6).unwrap();
+        assert_eq!(cursor.read_u32().unwrap(), 0);
+        assert!(matches!(
+            cursor.read_u32(),
+            Err(PeError::OutOfBounds {
+                offset: 0x14,
+                size: 4
+            })
+        ));
+        //  a failed read doesn't move the cursor
+        assert_eq!(cursor.read_u16().unwrap(), 0);
+        assert!(cursor.read_u8().is_err());
+
+        assert!(Cursor::from_slice(&[0u8; 4], 2, 4).is_err());
+        assert!(Cursor::from_slice(&[0u8; 4], usize::MAX, 4).is_err());
+        assert!(u32_at(&[0u8; 4], 1).is_err());
 
-    pub fn read_u64(&mut self) -> u64 {
-        u64_from_bytes(&self.read(8))
+        let mut cursor = Cursor::new(&[0xff, 0xfe]);
+        assert!(matches!(
+            cursor.read_str(2),
+            Err(PeError::InvalidString { offset: 0 })
+        ));
+        assert_eq!(cstr_at(b"abc\0", 1).unwrap(), b"bc");
+        assert!(matches!(
+            cstr_at(b"abc", 0),
+            Err(PeError::InvalidString { offset: 0 })
+        ));
     }
 }
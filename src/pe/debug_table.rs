@@ -0,0 +1,476 @@
+#![allow(non_camel_case_types)]
+
+use crate::util::{read_u8_until_null, u16_from_bytes, u32_from_bytes};
+
+use super::{
+    cursor::Cursor, import_table::rva2foa, optional_header::ImageDataDirectory,
+    section_table::SectionTable, PeError,
+};
+
+const RSDS_SIGNATURE: u32 = 0x53445352;
+const NB10_SIGNATURE: u32 = 0x3031424e;
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#debug-directory-image-only
+pub fn get_debug_directory(
+    section_table: &SectionTable,
+    bytes: &[u8],
+    debug_dir: ImageDataDirectory,
+) -> Result<Vec<DebugDirectoryEntry>, PeError> {
+    if debug_dir.virtual_address == 0 || debug_dir.size == 0 {
+        return Err(PeError::MissingTable(
+            "The executable doesn't have a debug directory".to_string(),
+        ));
+    }
+    let debug_foa = rva2foa(debug_dir.virtual_address, section_table)? as usize;
+    let mut cursor = Cursor::from_slice(bytes, debug_foa, debug_dir.size as usize)?;
+    let mut entries = vec![];
+    for _ in 0..debug_dir.size / 28 {
+        let mut entry = DebugDirectoryEntry {
+            characteristics: cursor.read_u32()?,
+            time_date_stamp: cursor.read_u32()?,
+            major_version: cursor.read_u16()?,
+            minor_version: cursor.read_u16()?,
+            r#type: DebugType::from(cursor.read_u32()?),
+            size_of_data: cursor.read_u32()?,
+            address_of_raw_data: cursor.read_u32()?,
+            ptr_to_raw_data: cursor.read_u32()?,
+            data: DebugData::Raw(vec![]),
+        };
+        //  the data isn't always mapped, prefer the file pointer
+        let data_offset = match entry.ptr_to_raw_data {
+            0 => section_table.rva_to_offset(entry.address_of_raw_data),
+            offset => Some(offset),
+        };
+        //  an entry whose data isn't in the file keeps an empty payload, the others are still read
+        let raw_data = data_offset.and_then(|offset| {
+            bytes.get(offset as usize..offset as usize + entry.size_of_data as usize)
+        });
+        //  and a payload that doesn't decode is kept as is
+        if let Some(raw_data) = raw_data {
+            entry.data = DebugData::parse(&entry.r#type, raw_data)
+                .unwrap_or_else(|_| DebugData::Raw(raw_data.to_vec()));
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Aka IMAGE_DEBUG_DIRECTORY
+#[derive(Debug, Clone)]
+pub struct DebugDirectoryEntry {
+    pub characteristics: u32,
+    pub time_date_stamp: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub r#type: DebugType,
+    pub size_of_data: u32,
+    pub address_of_raw_data: u32,
+    pub ptr_to_raw_data: u32,
+
+    ///  not in MS docs
+    pub data: DebugData,
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#debug-type
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DebugType {
+    IMAGE_DEBUG_TYPE_UNKNOWN,
+    IMAGE_DEBUG_TYPE_COFF,
+    IMAGE_DEBUG_TYPE_CODEVIEW,
+    IMAGE_DEBUG_TYPE_FPO,
+    IMAGE_DEBUG_TYPE_MISC,
+    IMAGE_DEBUG_TYPE_EXCEPTION,
+    IMAGE_DEBUG_TYPE_FIXUP,
+    IMAGE_DEBUG_TYPE_OMAP_TO_SRC,
+    IMAGE_DEBUG_TYPE_OMAP_FROM_SRC,
+    IMAGE_DEBUG_TYPE_BORLAND,
+    IMAGE_DEBUG_TYPE_RESERVED10,
+    IMAGE_DEBUG_TYPE_CLSID,
+    IMAGE_DEBUG_TYPE_VC_FEATURE,
+    IMAGE_DEBUG_TYPE_POGO,
+    IMAGE_DEBUG_TYPE_ILTCG,
+    IMAGE_DEBUG_TYPE_MPX,
+    IMAGE_DEBUG_TYPE_REPRO,
+    IMAGE_DEBUG_TYPE_EMBEDDED_PORTABLE_PDB,
+    IMAGE_DEBUG_TYPE_SPGO,
+    IMAGE_DEBUG_TYPE_PDBCHECKSUM,
+    IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS,
+    /// Types that are newer than this parser
+    Other(u32),
+}
+
+impl From<u32> for DebugType {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => DebugType::IMAGE_DEBUG_TYPE_UNKNOWN,
+            1 => DebugType::IMAGE_DEBUG_TYPE_COFF,
+            2 => DebugType::IMAGE_DEBUG_TYPE_CODEVIEW,
+            3 => DebugType::IMAGE_DEBUG_TYPE_FPO,
+            4 => DebugType::IMAGE_DEBUG_TYPE_MISC,
+            5 => DebugType::IMAGE_DEBUG_TYPE_EXCEPTION,
+            6 => DebugType::IMAGE_DEBUG_TYPE_FIXUP,
+            7 => DebugType::IMAGE_DEBUG_TYPE_OMAP_TO_SRC,
+            8 => DebugType::IMAGE_DEBUG_TYPE_OMAP_FROM_SRC,
+            9 => DebugType::IMAGE_DEBUG_TYPE_BORLAND,
+            10 => DebugType::IMAGE_DEBUG_TYPE_RESERVED10,
+            11 => DebugType::IMAGE_DEBUG_TYPE_CLSID,
+            12 => DebugType::IMAGE_DEBUG_TYPE_VC_FEATURE,
+            13 => DebugType::IMAGE_DEBUG_TYPE_POGO,
+            14 => DebugType::IMAGE_DEBUG_TYPE_ILTCG,
+            15 => DebugType::IMAGE_DEBUG_TYPE_MPX,
+            16 => DebugType::IMAGE_DEBUG_TYPE_REPRO,
+            17 => DebugType::IMAGE_DEBUG_TYPE_EMBEDDED_PORTABLE_PDB,
+            18 => DebugType::IMAGE_DEBUG_TYPE_SPGO,
+            19 => DebugType::IMAGE_DEBUG_TYPE_PDBCHECKSUM,
+            20 => DebugType::IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS,
+            _ => DebugType::Other(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum DebugData {
+    CodeView(CodeViewInfo),
+    Pogo(PogoInfo),
+    VcFeature(VcFeatureInfo),
+    /// The hash the linker used instead of a timestamp, empty for older toolchains
+    Repro(Vec<u8>),
+    ExDllCharacteristics(ExDllCharacteristics),
+    /// Everything that isn't decoded
+    Raw(Vec<u8>),
+}
+
+impl DebugData {
+    fn parse(r#type: &DebugType, data: &[u8]) -> Result<DebugData, PeError> {
+        let truncated = || PeError::ParseError(format!("Truncated {:?} debug data", r#type));
+        let result = match r#type {
+            DebugType::IMAGE_DEBUG_TYPE_CODEVIEW => match CodeViewInfo::parse(data)? {
+                Some(info) => DebugData::CodeView(info),
+                None => DebugData::Raw(data.to_vec()),
+            },
+            DebugType::IMAGE_DEBUG_TYPE_POGO => DebugData::Pogo(PogoInfo::parse(data)?),
+            DebugType::IMAGE_DEBUG_TYPE_VC_FEATURE => {
+                if data.len() < 20 {
+                    return Err(truncated());
+                }
+                let mut cursor = Cursor::new(&data[..20]);
+                DebugData::VcFeature(VcFeatureInfo {
+                    pre_vc11: cursor.read_u32()?,
+                    c_cpp: cursor.read_u32()?,
+                    gs: cursor.read_u32()?,
+                    sdl: cursor.read_u32()?,
+                    guard_n: cursor.read_u32()?,
+                })
+            }
+            DebugType::IMAGE_DEBUG_TYPE_REPRO => match data.len() {
+                0 => DebugData::Repro(vec![]),
+                1..=3 => return Err(truncated()),
+                _ => {
+                    let length = u32_from_bytes(data) as usize;
+                    let hash = data.get(4..4 + length).ok_or_else(truncated)?;
+                    DebugData::Repro(hash.to_vec())
+                }
+            },
+            DebugType::IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS => {
+                if data.len() < 4 {
+                    return Err(truncated());
+                }
+                DebugData::ExDllCharacteristics(ExDllCharacteristics::from_bits_retain(
+                    u32_from_bytes(data),
+                ))
+            }
+            _ => DebugData::Raw(data.to_vec()),
+        };
+        Ok(result)
+    }
+}
+
+/// CodeView records, RSDS for PDB 7.0 and NB10 for PDB 2.0
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CodeViewInfo {
+    Rsds {
+        guid: [u8; 16],
+        age: u32,
+        pdb_path: String,
+    },
+    Nb10 {
+        offset: u32,
+        signature: u32,
+        age: u32,
+        pdb_path: String,
+    },
+}
+
+impl CodeViewInfo {
+    fn parse(data: &[u8]) -> Result<Option<CodeViewInfo>, PeError> {
+        let truncated = || PeError::ParseError("Truncated CodeView record".to_string());
+        if data.len() < 4 {
+            return Err(truncated());
+        }
+        let pdb_path = |start: usize| {
+            let path = data.get(start..).ok_or_else(truncated)?;
+            let end = path.iter().position(|b| *b == 0).unwrap_or(path.len());
+            Ok::<String, PeError>(String::from_utf8_lossy(&path[..end]).to_string())
+        };
+        match u32_from_bytes(data) {
+            RSDS_SIGNATURE => {
+                if data.len() < 24 {
+                    return Err(truncated());
+                }
+                Ok(Some(CodeViewInfo::Rsds {
+                    guid: data[4..20].try_into().unwrap(),
+                    age: u32_from_bytes(&data[20..]),
+                    pdb_path: pdb_path(24)?,
+                }))
+            }
+            NB10_SIGNATURE => {
+                if data.len() < 16 {
+                    return Err(truncated());
+                }
+                Ok(Some(CodeViewInfo::Nb10 {
+                    offset: u32_from_bytes(&data[4..]),
+                    signature: u32_from_bytes(&data[8..]),
+                    age: u32_from_bytes(&data[12..]),
+                    pdb_path: pdb_path(16)?,
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn pdb_path(&self) -> &str {
+        match self {
+            CodeViewInfo::Rsds { pdb_path, .. } | CodeViewInfo::Nb10 { pdb_path, .. } => pdb_path,
+        }
+    }
+
+    pub fn age(&self) -> u32 {
+        match self {
+            CodeViewInfo::Rsds { age, .. } | CodeViewInfo::Nb10 { age, .. } => *age,
+        }
+    }
+
+    /// The GUID in its registry format, e.g. `0E8923A1-8AD9-4E55-A324-AC681991497F`
+    pub fn guid_string(&self) -> Option<String> {
+        match self {
+            CodeViewInfo::Rsds { guid, .. } => Some(format!(
+                "{:08X}-{:04X}-{:04X}-{}-{}",
+                u32_from_bytes(&guid[0..4]),
+                u16_from_bytes(&guid[4..6]),
+                u16_from_bytes(&guid[6..8]),
+                hex_upper(&guid[8..10]),
+                hex_upper(&guid[10..16])
+            )),
+            CodeViewInfo::Nb10 { .. } => None,
+        }
+    }
+
+    /// The directory name a symbol server stores the PDB under, GUID (or signature) + age
+    pub fn symbol_server_id(&self) -> String {
+        match self {
+            CodeViewInfo::Rsds { age, .. } => {
+                format!("{}{:X}", self.guid_string().unwrap().replace('-', ""), age)
+            }
+            CodeViewInfo::Nb10 { signature, age, .. } => format!("{:08X}{:X}", signature, age),
+        }
+    }
+}
+
+fn hex_upper(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// Profile guided optimization records, undocumented
+#[derive(Debug, Clone)]
+pub struct PogoInfo {
+    /// `PGU\0`, `PGI\0` or `LTCG`
+    pub signature: u32,
+    pub entries: Vec<PogoEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PogoEntry {
+    pub rva: u32,
+    pub size: u32,
+    pub name: String,
+}
+
+impl PogoInfo {
+    fn parse(data: &[u8]) -> Result<PogoInfo, PeError> {
+        if data.len() < 4 {
+            return Err(PeError::ParseError("Truncated POGO debug data".to_string()));
+        }
+        let signature = u32_from_bytes(data);
+        let mut entries = vec![];
+        let mut offset = 4;
+        //  rva, size and a null terminated name padded to 4 bytes
+        while offset + 9 <= data.len() {
+            let rva = u32_from_bytes(&data[offset..]);
+            let size = u32_from_bytes(&data[offset + 4..]);
+            if !data[offset + 8..].contains(&0) {
+                break;
+            }
+            let name = read_u8_until_null(offset + 8, data);
+            offset = (offset + 8 + name.len() + 1 + 3) & !3;
+            entries.push(PogoEntry {
+                rva,
+                size,
+                name: String::from_utf8_lossy(name).to_string(),
+            });
+        }
+        Ok(PogoInfo { signature, entries })
+    }
+}
+
+/// Counts of the objects built with each security feature, undocumented
+#[derive(Debug, Clone)]
+pub struct VcFeatureInfo {
+    pub pre_vc11: u32,
+    pub c_cpp: u32,
+    pub gs: u32,
+    pub sdl: u32,
+    pub guard_n: u32,
+}
+
+bitflags::bitflags! {
+    /// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#extended-dll-characteristics
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct ExDllCharacteristics: u32 {
+        const IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT = 0x0001;
+        const IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT_STRICT_MODE = 0x0002;
+        const IMAGE_DLLCHARACTERISTICS_EX_CET_SET_CONTEXT_IP_VALIDATION_RELAXED_MODE = 0x0004;
+        const IMAGE_DLLCHARACTERISTICS_EX_CET_DYNAMIC_APIS_ALLOW_IN_PROC = 0x0008;
+        const IMAGE_DLLCHARACTERISTICS_EX_CET_RESERVED_1 = 0x0010;
+        const IMAGE_DLLCHARACTERISTICS_EX_CET_RESERVED_2 = 0x0020;
+        const IMAGE_DLLCHARACTERISTICS_EX_FORWARD_CFI_COMPAT = 0x0040;
+        const IMAGE_DLLCHARACTERISTICS_EX_HOTPATCH_COMPATIBLE = 0x0080;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pe::{
+        optional_header::ImageDataDirectory,
+        section_table::SectionHeader,
+        test_util::{put_bytes, put_u32, section, section_table},
+        PeError, PortableExecutable,
+    };
+
+    use super::{get_debug_directory, CodeViewInfo, DebugData, DebugType, PogoInfo};
+
+    #[test]
+    fn parses_codeview_and_vc_feature() {
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        let entries = pe.debug_directory().unwrap();
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(entries[0].r#type, DebugType::IMAGE_DEBUG_TYPE_CODEVIEW);
+        let DebugData::CodeView(codeview) = &entries[0].data else {
+            panic!("expected a CodeView record, got {:?}", entries[0].data);
+        };
+        assert_eq!(
+            codeview.guid_string().unwrap(),
+            "0E8923A1-8AD9-4E55-A324-AC681991497F"
+        );
+        assert_eq!(codeview.age(), 1);
+        assert_eq!(
+            codeview.pdb_path(),
+            "G:\\code\\cpp\\example_executable_x86\\Debug\\example_executable_x86.pdb"
+        );
+        assert_eq!(
+            codeview.symbol_server_id(),
+            "0E8923A18AD94E55A324AC681991497F1"
+        );
+        assert_eq!(pe.codeview().unwrap(), Some(codeview.clone()));
+
+        let DebugData::VcFeature(features) = &entries[1].data else {
+            panic!("expected VC feature counts, got {:?}", entries[1].data);
+        };
+        assert_eq!(features.c_cpp, 0x27);
+        assert_eq!(features.gs, 0x27);
+        assert_eq!(features.sdl, 1);
+        assert_eq!(features.guard_n, 0x26);
+
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        assert!(matches!(
+            pe.debug_directory(),
+            Err(PeError::MissingTable(_))
+        ));
+    }
+
+    #[test]
+    fn skips_unmapped_entries() {
+        let bss = SectionHeader {
+            size_of_raw_data: 0,
+            ..section(".bss", 0x2000, 0, 0x100)
+        };
+        let section_table = section_table([section(".rdata", 0x1000, 0, 0x100), bss]);
+        let mut bytes = vec![0u8; 0x100];
+        //  a CodeView record in uninitialized data
+        put_u32(&mut bytes, 12, 2);
+        put_u32(&mut bytes, 16, 24);
+        put_u32(&mut bytes, 20, 0x2000);
+        //  followed by VC feature counts in the file
+        put_u32(&mut bytes, 28 + 12, 12);
+        put_u32(&mut bytes, 28 + 16, 20);
+        put_u32(&mut bytes, 28 + 20, 0x1040);
+        put_u32(&mut bytes, 28 + 24, 0x40);
+        put_u32(&mut bytes, 0x44, 5);
+        let dir = ImageDataDirectory {
+            virtual_address: 0x1000,
+            size: 56,
+            tag: "debug".to_string(),
+        };
+        let entries = get_debug_directory(&section_table, &bytes, dir).unwrap();
+        assert!(matches!(&entries[0].data, DebugData::Raw(data) if data.is_empty()));
+        assert!(matches!(&entries[1].data, DebugData::VcFeature(features) if features.c_cpp == 5));
+    }
+
+    #[test]
+    fn keeps_undecodable_entries_raw() {
+        let section_table = section_table([section(".rdata", 0x1000, 0, 0x100)]);
+        let mut bytes = vec![0u8; 0x100];
+        //  an RSDS record cut short before its GUID ends
+        put_u32(&mut bytes, 12, 2);
+        put_u32(&mut bytes, 16, 8);
+        put_u32(&mut bytes, 20, 0x1040);
+        put_u32(&mut bytes, 24, 0x40);
+        put_bytes(&mut bytes, 0x40, b"RSDSsola");
+        //  followed by a valid one
+        put_u32(&mut bytes, 28 + 12, 2);
+        put_u32(&mut bytes, 28 + 16, 32);
+        put_u32(&mut bytes, 28 + 20, 0x1060);
+        put_u32(&mut bytes, 28 + 24, 0x60);
+        put_bytes(&mut bytes, 0x60, b"RSDS");
+        put_u32(&mut bytes, 0x60 + 20, 3);
+        put_bytes(&mut bytes, 0x60 + 24, b"a.pdb");
+        let dir = ImageDataDirectory {
+            virtual_address: 0x1000,
+            size: 56,
+            tag: "debug".to_string(),
+        };
+        let entries = get_debug_directory(&section_table, &bytes, dir).unwrap();
+        assert!(matches!(&entries[0].data, DebugData::Raw(data) if data == b"RSDSsola"));
+        assert!(matches!(
+            &entries[1].data,
+            DebugData::CodeView(CodeViewInfo::Rsds { age: 3, pdb_path, .. }) if pdb_path == "a.pdb"
+        ));
+    }
+
+    #[test]
+    fn parses_pogo() {
+        let mut data = b"PGU\0".to_vec();
+        data.extend_from_slice(&0x1000u32.to_le_bytes());
+        data.extend_from_slice(&0x20u32.to_le_bytes());
+        data.extend_from_slice(b".text$mn\0\0\0\0");
+        data.extend_from_slice(&0x2000u32.to_le_bytes());
+        data.extend_from_slice(&0x8u32.to_le_bytes());
+        data.extend_from_slice(b".rdata\0\0");
+        let pogo = PogoInfo::parse(&data).unwrap();
+        assert_eq!(pogo.signature, 0x00554750);
+        assert_eq!(pogo.entries.len(), 2);
+        assert_eq!(pogo.entries[0].name, ".text$mn");
+        assert_eq!(pogo.entries[1].rva, 0x2000);
+        assert_eq!(pogo.entries[1].name, ".rdata");
+    }
+}
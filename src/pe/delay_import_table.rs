@@ -0,0 +1,249 @@
+use crate::util::{u32_from_bytes, u64_from_bytes};
+
+use super::{
+    cursor::{cstr_at, Cursor},
+    import_table::{read_import_lookup_table, rva2foa, ImportLookupTable},
+    optional_header::{ExecutableKind, ImageDataDirectory},
+    section_table::SectionTable,
+    PeError,
+};
+
+//  set on every descriptor emitted by VC7 and later, older linkers stored VAs
+const DELAY_ATTRIBUTE_RVA_BASED: u32 = 0x1;
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#delay-load-import-tables-image-only
+pub fn get_delay_import_table(
+    section_table: &SectionTable,
+    bytes: &[u8],
+    exec_kind: &ExecutableKind,
+    image_base: u64,
+    delay_import_table_dir: ImageDataDirectory,
+) -> Result<DelayImportTable, PeError> {
+    if delay_import_table_dir.virtual_address == 0 || delay_import_table_dir.size == 0 {
+        return Err(PeError::MissingTable(
+            "The executable doesn't have a delay import table".to_string(),
+        ));
+    }
+    let delay_import_foa = rva2foa(delay_import_table_dir.virtual_address, section_table)? as usize;
+    let mut cursor = Cursor::from_slice(
+        bytes,
+        delay_import_foa,
+        delay_import_table_dir.size as usize,
+    )?;
+    let mut descriptors = vec![];
+    for _ in 0..delay_import_table_dir.size / 32 {
+        let mut entry = DelayImportDescriptor {
+            attributes: cursor.read_u32()?,
+            name_rva: cursor.read_u32()?,
+            module_handle_rva: cursor.read_u32()?,
+            delay_import_address_table_rva: cursor.read_u32()?,
+            delay_import_name_table_rva: cursor.read_u32()?,
+            bound_delay_import_table_rva: cursor.read_u32()?,
+            unload_delay_import_table_rva: cursor.read_u32()?,
+            timedate_stamp: cursor.read_u32()?,
+
+            name: "".to_string(),
+            import_name_table: ImportLookupTable { entries: vec![] },
+            bound_delay_import_table: vec![],
+            unload_delay_import_table: vec![],
+        };
+        if entry.name_rva == 0 && entry.delay_import_address_table_rva == 0 {
+            break;
+        }
+        let name_base = match entry.attributes & DELAY_ATTRIBUTE_RVA_BASED {
+            0 => image_base,
+            _ => 0,
+        };
+        entry.normalize_vas(name_base);
+
+        let name_offset = rva2foa(entry.name_rva, section_table)?;
+        entry.name = String::from_utf8_lossy(cstr_at(bytes, name_offset as usize)?).to_string();
+        entry.import_name_table = read_import_lookup_table(
+            section_table,
+            bytes,
+            exec_kind,
+            entry.delay_import_name_table_rva,
+            entry.delay_import_address_table_rva,
+            name_base,
+        )?;
+        let count = entry.import_name_table.entries.len();
+        entry.bound_delay_import_table = read_thunks(
+            section_table,
+            bytes,
+            exec_kind,
+            entry.bound_delay_import_table_rva,
+            count,
+        );
+        entry.unload_delay_import_table = read_thunks(
+            section_table,
+            bytes,
+            exec_kind,
+            entry.unload_delay_import_table_rva,
+            count,
+        );
+        descriptors.push(entry);
+    }
+    Ok(DelayImportTable { descriptors })
+}
+
+/// Reads at most `count` pointer sized values, the optional tables mirror the IAT
+fn read_thunks(
+    section_table: &SectionTable,
+    bytes: &[u8],
+    exec_kind: &ExecutableKind,
+    rva: u32,
+    count: usize,
+) -> Vec<u64> {
+    if rva == 0 {
+        return vec![];
+    }
+    let size = match exec_kind {
+        ExecutableKind::PE32 => 4,
+        ExecutableKind::PE32_PLUS => 8,
+    };
+    let Ok(foa) = rva2foa(rva, section_table) else {
+        return vec![];
+    };
+    let foa = foa as usize;
+    (0..count)
+        .map(|idx| foa + idx * size)
+        .take_while(|offset| offset + size <= bytes.len())
+        .map(|offset| match exec_kind {
+            ExecutableKind::PE32 => u32_from_bytes(&bytes[offset..]) as u64,
+            ExecutableKind::PE32_PLUS => u64_from_bytes(&bytes[offset..]),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct DelayImportTable {
+    pub descriptors: Vec<DelayImportDescriptor>,
+}
+
+impl DelayImportTable {
+    pub fn get_by_name(&self, dll_name: &str) -> Option<&DelayImportDescriptor> {
+        self.descriptors
+            .iter()
+            .find(|descriptor| descriptor.name.eq_ignore_ascii_case(dll_name))
+    }
+}
+
+/// Aka ImgDelayDescr, all the RVAs are normalized even for VA based descriptors
+#[derive(Debug, Clone)]
+pub struct DelayImportDescriptor {
+    pub attributes: u32,
+    /// DLL Name RVA
+    pub name_rva: u32,
+    /// Where the loader helper stores the HMODULE after loading the DLL
+    pub module_handle_rva: u32,
+    pub delay_import_address_table_rva: u32,
+    pub delay_import_name_table_rva: u32,
+    pub bound_delay_import_table_rva: u32,
+    pub unload_delay_import_table_rva: u32,
+    pub timedate_stamp: u32,
+
+    ///  not in MS docs
+    pub name: String,
+    /// Delay Import Name Table, `func_ptr_address` points into the delay IAT
+    pub import_name_table: ImportLookupTable,
+    /// Bound addresses of the imports, empty if the DLL isn't bound
+    pub bound_delay_import_table: Vec<u64>,
+    /// Copy of the original delay IAT, empty if the DLL can't be unloaded
+    pub unload_delay_import_table: Vec<u64>,
+}
+
+impl DelayImportDescriptor {
+    fn normalize_vas(&mut self, image_base: u64) {
+        if image_base == 0 {
+            return;
+        }
+        let to_rva = |va: u32| match va {
+            0 => 0,
+            va => (va as u64).wrapping_sub(image_base) as u32,
+        };
+        self.name_rva = to_rva(self.name_rva);
+        self.module_handle_rva = to_rva(self.module_handle_rva);
+        self.delay_import_address_table_rva = to_rva(self.delay_import_address_table_rva);
+        self.delay_import_name_table_rva = to_rva(self.delay_import_name_table_rva);
+        self.bound_delay_import_table_rva = to_rva(self.bound_delay_import_table_rva);
+        self.unload_delay_import_table_rva = to_rva(self.unload_delay_import_table_rva);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pe::{
+        import_table::FuncAddress,
+        optional_header::{ExecutableKind, ImageDataDirectory},
+        section_table::SectionTable,
+        test_util::{put_bytes, put_u16, put_u32, section, section_table},
+        PeError, PortableExecutable,
+    };
+
+    use super::get_delay_import_table;
+
+    fn fixture(attributes: u32, base: u32) -> (SectionTable, Vec<u8>) {
+        let section_table = section_table([section(".didat", 0x2000, 0, 0x100)]);
+        let mut bytes = vec![0u8; 0x100];
+        //  one descriptor followed by the null descriptor
+        put_u32(&mut bytes, 0x00, attributes);
+        put_u32(&mut bytes, 0x04, base + 0x2080);
+        put_u32(&mut bytes, 0x08, base + 0x20f0);
+        put_u32(&mut bytes, 0x0c, base + 0x2040);
+        put_u32(&mut bytes, 0x10, base + 0x2050);
+        put_u32(&mut bytes, 0x18, base + 0x2060);
+        //  INT: a hint/name entry and an ordinal
+        put_u32(&mut bytes, 0x50, base + 0x2090);
+        put_u32(&mut bytes, 0x54, 0x80000003);
+        //  unload table is a copy of the original IAT
+        put_u32(&mut bytes, 0x60, 0x401000);
+        put_u32(&mut bytes, 0x64, 0x401010);
+        put_bytes(&mut bytes, 0x80, "XINPUT1_3.dll");
+        put_u16(&mut bytes, 0x90, 2);
+        put_bytes(&mut bytes, 0x92, "XInputGetState");
+        (section_table, bytes)
+    }
+
+    #[test]
+    fn parses_descriptors() {
+        for (attributes, base) in [(1, 0), (0, 0x400000)] {
+            let (section_table, bytes) = fixture(attributes, base);
+            let dir = ImageDataDirectory {
+                virtual_address: 0x2000,
+                size: 64,
+                tag: "delay_import_descriptor".to_string(),
+            };
+            let table = get_delay_import_table(
+                &section_table,
+                &bytes,
+                &ExecutableKind::PE32,
+                0x400000,
+                dir,
+            )
+            .unwrap();
+            assert_eq!(table.descriptors.len(), 1);
+            let xinput = table.get_by_name("xinput1_3.dll").unwrap();
+            assert_eq!(xinput.module_handle_rva, 0x20f0);
+            assert_eq!(xinput.delay_import_address_table_rva, 0x2040);
+            let entries = &xinput.import_name_table.entries;
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[0].name().as_deref(), Some("XInputGetState"));
+            assert!(matches!(
+                entries[1].func_ptr_address(),
+                FuncAddress::X86(0x2044)
+            ));
+            assert_eq!(entries[1].ordinal(), Some(3));
+            assert!(xinput.bound_delay_import_table.is_empty());
+            assert_eq!(xinput.unload_delay_import_table, vec![0x401000, 0x401010]);
+        }
+    }
+
+    #[test]
+    fn missing_table() {
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        assert!(matches!(
+            pe.delay_import_table(),
+            Err(PeError::MissingTable(_))
+        ));
+    }
+}
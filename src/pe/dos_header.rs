@@ -0,0 +1,86 @@
+use super::{cursor::Cursor, PeError};
+
+const DOS_SIGNATURE: u16 = 0x5a4d;
+const DOS_HEADER_SIZE: usize = 0x40;
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#ms-dos-stub-image-only
+pub fn parse_dos_header(cursor: &mut Cursor) -> Result<DosHeader, PeError> {
+    if cursor.bytes.len() < DOS_HEADER_SIZE {
+        return Err(PeError::ParseError(
+            "The file is too small to hold a DOS header".to_string(),
+        ));
+    }
+    let read_words = |cursor: &mut Cursor, words: &mut [u16]| {
+        for word in words.iter_mut() {
+            *word = cursor.read_u16()?;
+        }
+        Ok::<(), PeError>(())
+    };
+    let mut header = DosHeader {
+        e_magic: cursor.read_u16()?,
+        e_cblp: cursor.read_u16()?,
+        e_cp: cursor.read_u16()?,
+        e_crlc: cursor.read_u16()?,
+        e_cparhdr: cursor.read_u16()?,
+        e_minalloc: cursor.read_u16()?,
+        e_maxalloc: cursor.read_u16()?,
+        e_ss: cursor.read_u16()?,
+        e_sp: cursor.read_u16()?,
+        e_csum: cursor.read_u16()?,
+        e_ip: cursor.read_u16()?,
+        e_cs: cursor.read_u16()?,
+        e_lfarlc: cursor.read_u16()?,
+        e_ovno: cursor.read_u16()?,
+        e_res: [0; 4],
+        e_oemid: 0,
+        e_oeminfo: 0,
+        e_res2: [0; 10],
+        e_lfanew: 0,
+    };
+    read_words(cursor, &mut header.e_res)?;
+    header.e_oemid = cursor.read_u16()?;
+    header.e_oeminfo = cursor.read_u16()?;
+    read_words(cursor, &mut header.e_res2)?;
+    header.e_lfanew = cursor.read_u32()?;
+
+    if header.e_magic != DOS_SIGNATURE {
+        return Err(PeError::ParseError(format!(
+            "Invalid DOS signature: {:#x}",
+            header.e_magic
+        )));
+    }
+    if (header.e_lfanew as usize) < DOS_HEADER_SIZE
+        || header.e_lfanew as usize + 4 > cursor.bytes.len()
+    {
+        return Err(PeError::ParseError(format!(
+            "e_lfanew points outside of the file: {:#x}",
+            header.e_lfanew
+        )));
+    }
+    Ok(header)
+}
+
+/// Aka IMAGE_DOS_HEADER
+#[derive(Debug, Clone)]
+pub struct DosHeader {
+    pub e_magic: u16,
+    pub e_cblp: u16,
+    pub e_cp: u16,
+    pub e_crlc: u16,
+    pub e_cparhdr: u16,
+    pub e_minalloc: u16,
+    pub e_maxalloc: u16,
+    pub e_ss: u16,
+    pub e_sp: u16,
+    pub e_csum: u16,
+    pub e_ip: u16,
+    pub e_cs: u16,
+    pub e_lfarlc: u16,
+    pub e_ovno: u16,
+    pub e_res: [u16; 4],
+    pub e_oemid: u16,
+    pub e_oeminfo: u16,
+    pub e_res2: [u16; 10],
+    /// File offset of the PE signature
+    pub e_lfanew: u32,
+}
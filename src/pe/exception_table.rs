@@ -0,0 +1,475 @@
+#![allow(non_camel_case_types)]
+
+use crate::util::{u16_from_bytes, u32_from_bytes};
+
+use super::{
+    cursor::Cursor, file_header::Machine, import_table::rva2foa,
+    optional_header::ImageDataDirectory, section_table::SectionTable, PeError,
+};
+
+//  chained unwind info can point back to itself in broken binaries
+const MAX_CHAIN_DEPTH: usize = 32;
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#the-pdata-section
+pub fn get_exception_table(
+    section_table: &SectionTable,
+    bytes: &[u8],
+    machine: &Machine,
+    exception_table_dir: ImageDataDirectory,
+) -> Result<ExceptionTable, PeError> {
+    if exception_table_dir.virtual_address == 0 || exception_table_dir.size == 0 {
+        return Err(PeError::MissingTable(
+            "The executable doesn't have an exception table".to_string(),
+        ));
+    }
+    if *machine != Machine::IMAGE_FILE_MACHINE_AMD64 {
+        return Err(PeError::ParseError(format!(
+            "Only x64 exception tables are supported, found {:?}",
+            machine
+        )));
+    }
+    let pdata_foa = rva2foa(exception_table_dir.virtual_address, section_table)? as usize;
+    let mut cursor = Cursor::from_slice(bytes, pdata_foa, exception_table_dir.size as usize)?;
+    let mut functions = vec![];
+    for _ in 0..exception_table_dir.size / 12 {
+        let begin_address = cursor.read_u32()?;
+        let end_address = cursor.read_u32()?;
+        let unwind_info_address = cursor.read_u32()?;
+        if begin_address == 0 && end_address == 0 && unwind_info_address == 0 {
+            break;
+        }
+        //  a broken unwind info only costs its own entry
+        functions.push(RuntimeFunction {
+            begin_address,
+            end_address,
+            unwind_info_address,
+            unwind_info: parse_unwind_info(section_table, bytes, unwind_info_address, 0).ok(),
+        });
+    }
+    Ok(ExceptionTable { functions })
+}
+
+/// https://learn.microsoft.com/en-us/cpp/build/exception-handling-x64#struct-unwind_info
+fn parse_unwind_info(
+    section_table: &SectionTable,
+    bytes: &[u8],
+    rva: u32,
+    depth: usize,
+) -> Result<UnwindInfo, PeError> {
+    if depth >= MAX_CHAIN_DEPTH {
+        return Err(PeError::ParseError(format!(
+            "The unwind info chain at {:#x} is too long",
+            rva
+        )));
+    }
+    let foa = rva2foa(rva, section_table)? as usize;
+    let truncated = || PeError::ParseError(format!("Truncated unwind info at {:#x}", rva));
+    let header = bytes.get(foa..foa + 4).ok_or_else(truncated)?;
+    let count_of_codes = header[2];
+    let flags = UnwindFlags::from_bits_retain(header[0] >> 3);
+    //  the code array is always padded to an even number of slots
+    let slots_len = ((count_of_codes as usize + 1) & !1) * 2;
+    let slots = bytes
+        .get(foa + 4..foa + 4 + slots_len)
+        .ok_or_else(truncated)?
+        .chunks_exact(2)
+        .map(u16_from_bytes)
+        .collect::<Vec<u16>>();
+    let mut info = UnwindInfo {
+        version: header[0] & 0x7,
+        flags,
+        size_of_prolog: header[1],
+        count_of_codes,
+        frame_register: match header[3] & 0xF {
+            0 => None,
+            register => Some(Register::from(register)),
+        },
+        frame_offset: (header[3] >> 4) as u32 * 16,
+        unwind_codes: parse_unwind_codes(&slots[..count_of_codes as usize], header[0] & 0x7)?,
+        handler: None,
+        chained: None,
+    };
+
+    let trailer = foa + 4 + slots_len;
+    if flags.contains(UnwindFlags::UNW_FLAG_CHAININFO) {
+        let chained = bytes.get(trailer..trailer + 12).ok_or_else(truncated)?;
+        let unwind_info_address = u32_from_bytes(&chained[8..]);
+        info.chained = Some(Box::new(RuntimeFunction {
+            begin_address: u32_from_bytes(chained),
+            end_address: u32_from_bytes(&chained[4..]),
+            unwind_info_address,
+            unwind_info: parse_unwind_info(section_table, bytes, unwind_info_address, depth + 1)
+                .ok(),
+        }));
+    } else if flags.intersects(UnwindFlags::UNW_FLAG_EHANDLER | UnwindFlags::UNW_FLAG_UHANDLER) {
+        let handler = bytes.get(trailer..trailer + 4).ok_or_else(truncated)?;
+        info.handler = Some(u32_from_bytes(handler));
+    }
+    Ok(info)
+}
+
+fn parse_unwind_codes(slots: &[u16], version: u8) -> Result<Vec<UnwindCode>, PeError> {
+    let mut codes = vec![];
+    let mut idx = 0;
+    while idx < slots.len() {
+        let slot = slots[idx];
+        let code_offset = slot as u8;
+        let op = ((slot >> 8) & 0xF) as u8;
+        let info = (slot >> 12) as u8;
+        let missing = || {
+            PeError::ParseError(format!(
+                "Unwind code {:#x} is missing its operand slots",
+                op
+            ))
+        };
+        let next = |n: usize| slots.get(idx + n).copied().ok_or_else(missing);
+        let next_u32 = || Ok::<u32, PeError>(next(1)? as u32 | (next(2)? as u32) << 16);
+
+        let (operation, used_slots) = match op {
+            0 => (UnwindOperation::PushNonVol(Register::from(info)), 1),
+            1 if info == 0 => (UnwindOperation::AllocLarge(next(1)? as u32 * 8), 2),
+            1 => (UnwindOperation::AllocLarge(next_u32()?), 3),
+            2 => (UnwindOperation::AllocSmall(info as u32 * 8 + 8), 1),
+            3 => (UnwindOperation::SetFpReg, 1),
+            4 => (
+                UnwindOperation::SaveNonVol {
+                    register: Register::from(info),
+                    offset: next(1)? as u32 * 8,
+                },
+                2,
+            ),
+            5 => (
+                UnwindOperation::SaveNonVol {
+                    register: Register::from(info),
+                    offset: next_u32()?,
+                },
+                3,
+            ),
+            //  version 2 reused the slots of the old SAVE_XMM/SAVE_XMM_FAR codes
+            6 if version >= 2 => (
+                UnwindOperation::Epilog {
+                    info,
+                    data: next(1)?,
+                },
+                2,
+            ),
+            6 => (
+                UnwindOperation::SaveXmm {
+                    register: info,
+                    offset: next(1)? as u32 * 8,
+                },
+                2,
+            ),
+            7 if version >= 2 => (UnwindOperation::SpareCode, 3),
+            7 => (
+                UnwindOperation::SaveXmm {
+                    register: info,
+                    offset: next_u32()?,
+                },
+                3,
+            ),
+            8 => (
+                UnwindOperation::SaveXmm128 {
+                    register: info,
+                    offset: next(1)? as u32 * 16,
+                },
+                2,
+            ),
+            9 => (
+                UnwindOperation::SaveXmm128 {
+                    register: info,
+                    offset: next_u32()?,
+                },
+                3,
+            ),
+            10 => (
+                UnwindOperation::PushMachFrame {
+                    error_code: info == 1,
+                },
+                1,
+            ),
+            _ => {
+                return Err(PeError::ParseError(format!(
+                    "Tried to parse an invalid unwind operation: {:#x}",
+                    op
+                )))
+            }
+        };
+        codes.push(UnwindCode {
+            code_offset,
+            operation,
+        });
+        idx += used_slots;
+    }
+    Ok(codes)
+}
+
+#[derive(Debug, Clone)]
+pub struct ExceptionTable {
+    pub functions: Vec<RuntimeFunction>,
+}
+
+impl ExceptionTable {
+    /// The entry whose [begin, end) range contains `rva`
+    pub fn function_for_rva(&self, rva: u32) -> Option<&RuntimeFunction> {
+        //  the linker sorts the table by begin address
+        let idx = self
+            .functions
+            .partition_point(|function| function.begin_address <= rva);
+        let function = self.functions.get(idx.checked_sub(1)?)?;
+        (rva < function.end_address).then_some(function)
+    }
+}
+
+/// Aka RUNTIME_FUNCTION
+#[derive(Debug, Clone)]
+pub struct RuntimeFunction {
+    pub begin_address: u32,
+    pub end_address: u32,
+    pub unwind_info_address: u32,
+
+    ///  not in MS docs, `None` if the unwind info couldn't be read
+    pub unwind_info: Option<UnwindInfo>,
+}
+
+impl RuntimeFunction {
+    /// Follows the chained unwind info back to the entry of the function that owns this one
+    pub fn primary(&self) -> &RuntimeFunction {
+        let mut function = self;
+        while let Some(chained) = function
+            .unwind_info
+            .as_ref()
+            .and_then(|info| info.chained.as_ref())
+        {
+            function = chained;
+        }
+        function
+    }
+}
+
+/// https://learn.microsoft.com/en-us/cpp/build/exception-handling-x64#struct-unwind_info
+#[derive(Debug, Clone)]
+pub struct UnwindInfo {
+    pub version: u8,
+    pub flags: UnwindFlags,
+    pub size_of_prolog: u8,
+    pub count_of_codes: u8,
+    pub frame_register: Option<Register>,
+    /// Already scaled by 16
+    pub frame_offset: u32,
+    pub unwind_codes: Vec<UnwindCode>,
+    /// RVA of the language specific handler, with UNW_FLAG_EHANDLER or UNW_FLAG_UHANDLER
+    pub handler: Option<u32>,
+    /// The entry this one continues, with UNW_FLAG_CHAININFO
+    pub chained: Option<Box<RuntimeFunction>>,
+}
+
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct UnwindFlags: u8 {
+        const UNW_FLAG_EHANDLER = 0x1;
+        const UNW_FLAG_UHANDLER = 0x2;
+        const UNW_FLAG_CHAININFO = 0x4;
+    }
+}
+
+/// https://learn.microsoft.com/en-us/cpp/build/exception-handling-x64#struct-unwind_code
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UnwindCode {
+    /// Offset from the start of the prolog to the end of the instruction
+    pub code_offset: u8,
+    pub operation: UnwindOperation,
+}
+
+/// Sizes and offsets are already scaled
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum UnwindOperation {
+    PushNonVol(Register),
+    AllocLarge(u32),
+    AllocSmall(u32),
+    SetFpReg,
+    SaveNonVol {
+        register: Register,
+        offset: u32,
+    },
+    /// Only in version 1 unwind info
+    SaveXmm {
+        register: u8,
+        offset: u32,
+    },
+    /// Only in version 2 unwind info, undocumented
+    Epilog {
+        info: u8,
+        data: u16,
+    },
+    /// Only in version 2 unwind info, undocumented
+    SpareCode,
+    SaveXmm128 {
+        register: u8,
+        offset: u32,
+    },
+    PushMachFrame {
+        error_code: bool,
+    },
+}
+
+/// https://learn.microsoft.com/en-us/cpp/build/exception-handling-x64#operation-info
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Register {
+    RAX,
+    RCX,
+    RDX,
+    RBX,
+    RSP,
+    RBP,
+    RSI,
+    RDI,
+    R8,
+    R9,
+    R10,
+    R11,
+    R12,
+    R13,
+    R14,
+    R15,
+}
+
+impl From<u8> for Register {
+    fn from(value: u8) -> Self {
+        match value & 0xF {
+            0 => Register::RAX,
+            1 => Register::RCX,
+            2 => Register::RDX,
+            3 => Register::RBX,
+            4 => Register::RSP,
+            5 => Register::RBP,
+            6 => Register::RSI,
+            7 => Register::RDI,
+            8 => Register::R8,
+            9 => Register::R9,
+            10 => Register::R10,
+            11 => Register::R11,
+            12 => Register::R12,
+            13 => Register::R13,
+            14 => Register::R14,
+            _ => Register::R15,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pe::{
+        file_header::Machine,
+        optional_header::ImageDataDirectory,
+        test_util::{put_bytes, put_u16, put_u32, section, section_table},
+        PeError, PortableExecutable,
+    };
+
+    use super::{get_exception_table, parse_unwind_info, Register, UnwindFlags, UnwindOperation};
+
+    #[test]
+    fn parses_runtime_functions() {
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        let table = pe.exception_table().unwrap();
+        assert_eq!(table.functions.len(), 0x210 / 12);
+
+        let start = table.function_for_rva(0x1200).unwrap();
+        assert_eq!(start.begin_address, 0x1180);
+        assert_eq!(start.end_address, 0x13d0);
+        let info = start.unwind_info.as_ref().unwrap();
+        assert_eq!(info.size_of_prolog, 10);
+        assert_eq!(info.unwind_codes.len(), 6);
+        assert_eq!(
+            info.unwind_codes[0].operation,
+            UnwindOperation::AllocSmall(32)
+        );
+        assert_eq!(
+            info.unwind_codes[1].operation,
+            UnwindOperation::PushNonVol(Register::RBX)
+        );
+
+        let handled = table.function_for_rva(0x13d0).unwrap();
+        let handled = handled.unwind_info.as_ref().unwrap();
+        assert!(handled.flags.contains(UnwindFlags::UNW_FLAG_EHANDLER));
+        assert_eq!(handled.handler, Some(0x2580));
+
+        let relocator = table.function_for_rva(0x1920).unwrap();
+        let relocator = relocator.unwind_info.as_ref().unwrap();
+        assert_eq!(relocator.frame_register, Some(Register::RBP));
+        assert_eq!(relocator.frame_offset, 0x40);
+
+        //  the gap between __mingw_invalidParameterHandler and pre_c_init
+        assert!(table.function_for_rva(0x1005).is_none());
+        assert!(table.function_for_rva(0).is_none());
+
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        assert!(matches!(
+            pe.exception_table(),
+            Err(PeError::MissingTable(_))
+        ));
+    }
+
+    #[test]
+    fn follows_chained_unwind_info() {
+        let section_table = section_table([section(".xdata", 0x1000, 0, 0x100)]);
+        let mut bytes = vec![0u8; 0x100];
+        //  primary: push rbx, alloc 0x1000 (UWOP_ALLOC_LARGE with a 16 bit operand)
+        put_bytes(&mut bytes, 0, [0x01, 0x0b, 0x03, 0x00]);
+        put_bytes(&mut bytes, 4, [0x0b, 0x01]);
+        put_u16(&mut bytes, 6, 0x200);
+        put_bytes(&mut bytes, 8, [0x04, 0x30]);
+        //  chained: no codes, points back at the primary
+        put_bytes(&mut bytes, 0x20, [0x01 | (0x4 << 3), 0x00, 0x00, 0x00]);
+        put_u32(&mut bytes, 0x24, 0x3000);
+        put_u32(&mut bytes, 0x28, 0x3100);
+        put_u32(&mut bytes, 0x2c, 0x1000);
+
+        let info = parse_unwind_info(&section_table, &bytes, 0x1020, 0).unwrap();
+        assert!(info.flags.contains(UnwindFlags::UNW_FLAG_CHAININFO));
+        let chained = info.chained.as_ref().unwrap();
+        assert_eq!(chained.begin_address, 0x3000);
+        let codes = &chained.unwind_info.as_ref().unwrap().unwind_codes;
+        assert_eq!(codes[0].operation, UnwindOperation::AllocLarge(0x1000));
+        assert_eq!(
+            codes[1].operation,
+            UnwindOperation::PushNonVol(Register::RBX)
+        );
+    }
+
+    #[test]
+    fn keeps_functions_with_broken_unwind_info() {
+        let section_table = section_table([section(".pdata", 0x1000, 0, 0x100)]);
+        let mut bytes = vec![0u8; 0x100];
+        //  one function with a sub rsp, 8 and one whose unwind info is past the file
+        put_u32(&mut bytes, 0, 0x2000);
+        put_u32(&mut bytes, 4, 0x2010);
+        put_u32(&mut bytes, 8, 0x1080);
+        put_u32(&mut bytes, 12, 0x2010);
+        put_u32(&mut bytes, 16, 0x2020);
+        put_u32(&mut bytes, 20, 0x5000);
+        put_bytes(&mut bytes, 0x80, [0x01, 0x04, 0x01, 0x00, 0x04, 0x02]);
+        let dir = ImageDataDirectory {
+            virtual_address: 0x1000,
+            size: 24,
+            tag: "exception".to_string(),
+        };
+        let table = get_exception_table(
+            &section_table,
+            &bytes,
+            &Machine::IMAGE_FILE_MACHINE_AMD64,
+            dir,
+        )
+        .unwrap();
+        assert_eq!(table.functions.len(), 2);
+        let codes = &table.functions[0]
+            .unwind_info
+            .as_ref()
+            .unwrap()
+            .unwind_codes;
+        assert_eq!(codes[0].operation, UnwindOperation::AllocSmall(8));
+        let broken = table.function_for_rva(0x2018).unwrap();
+        assert_eq!(broken.unwind_info_address, 0x5000);
+        assert!(broken.unwind_info.is_none());
+    }
+}
@@ -0,0 +1,299 @@
+use super::{
+    cursor::{cstr_at, slice_at, u16_at, u32_at, Cursor},
+    import_table::rva2foa,
+    optional_header::ImageDataDirectory,
+    section_table::SectionTable,
+    PeError,
+};
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#the-edata-section-image-only
+pub fn get_export_table(
+    section_table: &SectionTable,
+    bytes: &[u8],
+    export_table_dir: ImageDataDirectory,
+) -> Result<ExportTable, PeError> {
+    if export_table_dir.virtual_address == 0 || export_table_dir.size == 0 {
+        return Err(PeError::MissingTable(
+            "The executable doesn't have an export table".to_string(),
+        ));
+    }
+    let export_foa = rva2foa(export_table_dir.virtual_address, section_table)? as usize;
+    let mut cursor = Cursor::from_slice(bytes, export_foa, 40)?;
+    let directory = ExportDirectoryTable {
+        export_flags: cursor.read_u32()?,
+        time_date_stamp: cursor.read_u32()?,
+        major_version: cursor.read_u16()?,
+        minor_version: cursor.read_u16()?,
+        name_rva: cursor.read_u32()?,
+        ordinal_base: cursor.read_u32()?,
+        address_table_entries: cursor.read_u32()?,
+        number_of_name_pointers: cursor.read_u32()?,
+        export_address_table_rva: cursor.read_u32()?,
+        name_pointer_rva: cursor.read_u32()?,
+        ordinal_table_rva: cursor.read_u32()?,
+    };
+
+    let read_string = |rva: u32| {
+        let offset = rva2foa(rva, section_table)? as usize;
+        Ok::<String, PeError>(String::from_utf8_lossy(cstr_at(bytes, offset)?).to_string())
+    };
+
+    //  every name pointer has a matching index into the export address table
+    let name_pointers_foa = rva2foa(directory.name_pointer_rva, section_table)? as usize;
+    let ordinals_foa = rva2foa(directory.ordinal_table_rva, section_table)? as usize;
+    let eat_foa = rva2foa(directory.export_address_table_rva, section_table)? as usize;
+    //  check the table fits before allocating a slot for every entry
+    slice_at(bytes, eat_foa, directory.address_table_entries as usize * 4)?;
+    let mut names: Vec<Option<String>> = vec![None; directory.address_table_entries as usize];
+    for i in 0..directory.number_of_name_pointers as usize {
+        let name_rva = u32_at(bytes, name_pointers_foa + i * 4)?;
+        let index = u16_at(bytes, ordinals_foa + i * 2)? as usize;
+        if let Some(name) = names.get_mut(index) {
+            *name = Some(read_string(name_rva)?);
+        }
+    }
+
+    let export_range = export_table_dir.virtual_address
+        ..export_table_dir
+            .virtual_address
+            .saturating_add(export_table_dir.size);
+    let mut entries = vec![];
+    for (index, name) in names.into_iter().enumerate() {
+        let rva = u32_at(bytes, eat_foa + index * 4)?;
+        //  unused slots in the export address table are zeroed
+        if rva == 0 {
+            continue;
+        }
+        let address = if export_range.contains(&rva) {
+            ExportAddress::Forwarder {
+                rva,
+                forwarder: read_string(rva)?,
+            }
+        } else {
+            ExportAddress::Export {
+                rva,
+                file_offset: section_table.rva_to_offset(rva),
+            }
+        };
+        entries.push(ExportEntry {
+            ordinal: directory.ordinal_base + index as u32,
+            name,
+            address,
+        });
+    }
+
+    Ok(ExportTable {
+        name: read_string(directory.name_rva)?,
+        ordinal_base: directory.ordinal_base,
+        directory,
+        entries,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportTable {
+    pub directory: ExportDirectoryTable,
+    /// The name of the DLL, as written by the linker
+    pub name: String,
+    pub ordinal_base: u32,
+    pub entries: Vec<ExportEntry>,
+}
+
+impl ExportTable {
+    pub fn named(&self) -> impl Iterator<Item = &ExportEntry> {
+        self.entries.iter().filter(|e| e.name.is_some())
+    }
+
+    pub fn ordinal_only(&self) -> impl Iterator<Item = &ExportEntry> {
+        self.entries.iter().filter(|e| e.name.is_none())
+    }
+
+    pub fn forwarded(&self) -> impl Iterator<Item = &ExportEntry> {
+        self.entries.iter().filter(|e| e.is_forwarded())
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&ExportEntry> {
+        self.entries
+            .iter()
+            .find(|e| e.name.as_deref() == Some(name))
+    }
+
+    pub fn get_by_ordinal(&self, ordinal: u32) -> Option<&ExportEntry> {
+        self.entries.iter().find(|e| e.ordinal == ordinal)
+    }
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#export-directory-table
+#[derive(Debug, Clone)]
+pub struct ExportDirectoryTable {
+    pub export_flags: u32,
+    pub time_date_stamp: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub name_rva: u32,
+    pub ordinal_base: u32,
+    pub address_table_entries: u32,
+    pub number_of_name_pointers: u32,
+    pub export_address_table_rva: u32,
+    pub name_pointer_rva: u32,
+    pub ordinal_table_rva: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportEntry {
+    /// Biased ordinal (ordinal base + index into the export address table)
+    pub ordinal: u32,
+    /// `None` for exports that are only reachable by ordinal
+    pub name: Option<String>,
+    pub address: ExportAddress,
+}
+
+impl ExportEntry {
+    pub fn is_forwarded(&self) -> bool {
+        matches!(self.address, ExportAddress::Forwarder { .. })
+    }
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#export-address-table
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ExportAddress {
+    Export {
+        rva: u32,
+        /// `None` for exports in uninitialized data, like a variable in .bss
+        file_offset: Option<u32>,
+    },
+    /// The RVA points inside the export section, to a string like `NTDLL.RtlAllocateHeap`
+    Forwarder { rva: u32, forwarder: String },
+}
+
+impl std::fmt::Display for ExportTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "name: {}", self.name)?;
+        writeln!(f, "ordinal_base: {}", self.ordinal_base)?;
+        for entry in &self.entries {
+            writeln!(f, "\tordinal: {}", entry.ordinal)?;
+            writeln!(
+                f,
+                "\tname: {}",
+                entry.name.as_deref().unwrap_or("[NAMELESS]")
+            )?;
+            match &entry.address {
+                ExportAddress::Export { rva, file_offset } => {
+                    writeln!(f, "\trva: {:#x}", rva)?;
+                    if let Some(file_offset) = file_offset {
+                        writeln!(f, "\tfile_offset: {:#x}", file_offset)?;
+                    }
+                }
+                ExportAddress::Forwarder { forwarder, .. } => {
+                    writeln!(f, "\tforwarder: {}", forwarder)?;
+                }
+            }
+            writeln!(f, "-------------------")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pe::{
+        optional_header::ImageDataDirectory,
+        section_table::{SectionHeader, SectionTable},
+        test_util::{put_bytes, put_u16, put_u32, section, section_table},
+        PeError, PortableExecutable,
+    };
+
+    use super::{get_export_table, ExportAddress};
+
+    /// .edata mapped at rva 0x1000, file offset 0x200, and an uninitialized .bss at 0x2000
+    fn edata_fixture() -> (SectionTable, Vec<u8>) {
+        let mut bytes = vec![0u8; 0x400];
+        let dir = 0x200;
+        put_u32(&mut bytes, dir + 12, 0x1100); // name
+        put_u32(&mut bytes, dir + 16, 5); // ordinal base
+        put_u32(&mut bytes, dir + 20, 4); // address table entries
+        put_u32(&mut bytes, dir + 24, 2); // number of name pointers
+        put_u32(&mut bytes, dir + 28, 0x1040); // export address table
+        put_u32(&mut bytes, dir + 32, 0x1060); // name pointers
+        put_u32(&mut bytes, dir + 36, 0x1070); // ordinals
+
+        put_u32(&mut bytes, 0x240, 0x1180);
+        put_u32(&mut bytes, 0x244, 0x2010);
+        put_u32(&mut bytes, 0x248, 0);
+        put_u32(&mut bytes, 0x24c, 0x1120);
+
+        put_u32(&mut bytes, 0x260, 0x1110);
+        put_u32(&mut bytes, 0x264, 0x1118);
+        put_u16(&mut bytes, 0x270, 0);
+        put_u16(&mut bytes, 0x272, 3);
+
+        put_bytes(&mut bytes, 0x300, "game.dll");
+        put_bytes(&mut bytes, 0x310, "Alloc");
+        put_bytes(&mut bytes, 0x318, "Heap");
+        put_bytes(&mut bytes, 0x320, "NTDLL.RtlAllocateHeap");
+        let bss = SectionHeader {
+            size_of_raw_data: 0,
+            ..section(".bss", 0x2000, 0, 0x100)
+        };
+        (
+            section_table([section(".edata", 0x1000, 0x200, 0x200), bss]),
+            bytes,
+        )
+    }
+
+    #[test]
+    fn parses_named_ordinal_and_forwarded_exports() {
+        let (section_table, bytes) = edata_fixture();
+        let dir = ImageDataDirectory {
+            virtual_address: 0x1000,
+            size: 0x140,
+            tag: "export_table".to_string(),
+        };
+        let table = get_export_table(&section_table, &bytes, dir).unwrap();
+        assert_eq!(table.name, "game.dll");
+        assert_eq!(table.ordinal_base, 5);
+        assert_eq!(table.entries.len(), 3);
+
+        let alloc = table.get_by_name("Alloc").unwrap();
+        assert_eq!(alloc.ordinal, 5);
+        assert_eq!(
+            alloc.address,
+            ExportAddress::Export {
+                rva: 0x1180,
+                file_offset: Some(0x380)
+            }
+        );
+
+        //  an exported variable in .bss has no file data
+        let by_ordinal = table.ordinal_only().collect::<Vec<_>>();
+        assert_eq!(by_ordinal.len(), 1);
+        assert_eq!(by_ordinal[0].ordinal, 6);
+        assert_eq!(
+            by_ordinal[0].address,
+            ExportAddress::Export {
+                rva: 0x2010,
+                file_offset: None
+            }
+        );
+
+        let heap = table.forwarded().next().unwrap();
+        assert_eq!(heap.name.as_deref(), Some("Heap"));
+        assert_eq!(heap.ordinal, 8);
+        assert_eq!(
+            heap.address,
+            ExportAddress::Forwarder {
+                rva: 0x1120,
+                forwarder: "NTDLL.RtlAllocateHeap".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn missing_export_table() {
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        assert!(matches!(
+            pe.get_export_table(),
+            Err(PeError::MissingTable(_))
+        ));
+    }
+}
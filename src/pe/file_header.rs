@@ -2,18 +2,22 @@
 
 use std::str::FromStr;
 
-use super::{cursor::Cursor, PeError};
+use super::{
+    cursor::Cursor,
+    symbol_table::{get_symbol_table, SymbolTable},
+    PeError,
+};
 
 /// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#coff-file-header-object-and-image
 pub fn parse_file_header(cursor: &mut Cursor) -> Result<FileHeader, PeError> {
     let header = FileHeader {
-        machine: Machine::try_from(cursor.read_u16())?,
-        number_of_sections: cursor.read_u16(),
-        time_date_stamp: cursor.read_u32(),
-        ptr_to_symbol_table: cursor.read_u32(),
-        number_of_symbols: cursor.read_u32(),
-        size_of_optional_header: cursor.read_u16(),
-        characteristics: format!("0x{:x}", cursor.read_u16())
+        machine: Machine::try_from(cursor.read_u16()?)?,
+        number_of_sections: cursor.read_u16()?,
+        time_date_stamp: cursor.read_u32()?,
+        ptr_to_symbol_table: cursor.read_u32()?,
+        number_of_symbols: cursor.read_u32()?,
+        size_of_optional_header: cursor.read_u16()?,
+        characteristics: format!("0x{:x}", cursor.read_u16()?)
             .parse::<Characteristic>()
             .map_err(|e| {
                 PeError::ParseError(format!(
@@ -37,9 +41,35 @@ pub struct FileHeader {
     pub characteristics: Characteristic,
 }
 
+impl FileHeader {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(20);
+        out.extend(u16::from(&self.machine).to_le_bytes());
+        out.extend(self.number_of_sections.to_le_bytes());
+        out.extend(self.time_date_stamp.to_le_bytes());
+        out.extend(self.ptr_to_symbol_table.to_le_bytes());
+        out.extend(self.number_of_symbols.to_le_bytes());
+        out.extend(self.size_of_optional_header.to_le_bytes());
+        out.extend(self.characteristics.bits().to_le_bytes());
+        out
+    }
+
+    /// The COFF symbol table and the string table after it. Objects have one, images only
+    /// if the linker kept it, e.g. MinGW without -s
+    pub fn symbol_table(&self, bytes: &[u8]) -> Result<SymbolTable, PeError> {
+        if self.ptr_to_symbol_table == 0 || self.number_of_symbols == 0 {
+            return Err(PeError::MissingTable(
+                "The file doesn't have a COFF symbol table".to_string(),
+            ));
+        }
+        get_symbol_table(bytes, self.ptr_to_symbol_table, self.number_of_symbols)
+    }
+}
+
 /// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#machine-types
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
 pub enum Machine {
+    #[default]
     IMAGE_FILE_MACHINE_UNKNOWN,
     IMAGE_FILE_MACHINE_ALPHA,
     IMAGE_FILE_MACHINE_ALPHA64,
@@ -47,6 +77,8 @@ pub enum Machine {
     IMAGE_FILE_MACHINE_AMD64,
     IMAGE_FILE_MACHINE_ARM,
     IMAGE_FILE_MACHINE_ARM64,
+    /// ARM64 code that interoperates with x64 code in the same process
+    IMAGE_FILE_MACHINE_ARM64EC,
     IMAGE_FILE_MACHINE_ARMNT,
     IMAGE_FILE_MACHINE_EBC,
     IMAGE_FILE_MACHINE_I386,
@@ -71,9 +103,40 @@ pub enum Machine {
     IMAGE_FILE_MACHINE_WCEMIPSV2,
 }
 
-impl Default for Machine {
-    fn default() -> Self {
-        Self::IMAGE_FILE_MACHINE_UNKNOWN
+impl From<&Machine> for u16 {
+    fn from(machine: &Machine) -> u16 {
+        match machine {
+            Machine::IMAGE_FILE_MACHINE_UNKNOWN => 0x0,
+            Machine::IMAGE_FILE_MACHINE_ALPHA => 0x184,
+            Machine::IMAGE_FILE_MACHINE_ALPHA64 => 0x284,
+            Machine::IMAGE_FILE_MACHINE_AM33 => 0x1d3,
+            Machine::IMAGE_FILE_MACHINE_AMD64 => 0x8664,
+            Machine::IMAGE_FILE_MACHINE_ARM => 0x1c0,
+            Machine::IMAGE_FILE_MACHINE_ARM64 => 0xaa64,
+            Machine::IMAGE_FILE_MACHINE_ARM64EC => 0xa641,
+            Machine::IMAGE_FILE_MACHINE_ARMNT => 0x1c4,
+            Machine::IMAGE_FILE_MACHINE_EBC => 0xebc,
+            Machine::IMAGE_FILE_MACHINE_I386 => 0x14c,
+            Machine::IMAGE_FILE_MACHINE_IA64 => 0x200,
+            Machine::IMAGE_FILE_MACHINE_LOONGARCH32 => 0x6232,
+            Machine::IMAGE_FILE_MACHINE_LOONGARCH64 => 0x6264,
+            Machine::IMAGE_FILE_MACHINE_M32R => 0x9041,
+            Machine::IMAGE_FILE_MACHINE_MIPS16 => 0x266,
+            Machine::IMAGE_FILE_MACHINE_MIPSFPU => 0x366,
+            Machine::IMAGE_FILE_MACHINE_MIPSFPU16 => 0x466,
+            Machine::IMAGE_FILE_MACHINE_POWERPC => 0x1f0,
+            Machine::IMAGE_FILE_MACHINE_POWERPCFP => 0x1f1,
+            Machine::IMAGE_FILE_MACHINE_R4000 => 0x166,
+            Machine::IMAGE_FILE_MACHINE_RISCV32 => 0x5032,
+            Machine::IMAGE_FILE_MACHINE_RISCV64 => 0x5064,
+            Machine::IMAGE_FILE_MACHINE_RISCV128 => 0x5128,
+            Machine::IMAGE_FILE_MACHINE_SH3 => 0x1a2,
+            Machine::IMAGE_FILE_MACHINE_SH3DSP => 0x1a3,
+            Machine::IMAGE_FILE_MACHINE_SH4 => 0x1a6,
+            Machine::IMAGE_FILE_MACHINE_SH5 => 0x1a8,
+            Machine::IMAGE_FILE_MACHINE_THUMB => 0x1c2,
+            Machine::IMAGE_FILE_MACHINE_WCEMIPSV2 => 0x169,
+        }
     }
 }
 
@@ -88,6 +151,7 @@ impl TryFrom<u16> for Machine {
             0x8664 => Ok(Self::IMAGE_FILE_MACHINE_AMD64),
             0x1c0 => Ok(Self::IMAGE_FILE_MACHINE_ARM),
             0xaa64 => Ok(Self::IMAGE_FILE_MACHINE_ARM64),
+            0xa641 => Ok(Self::IMAGE_FILE_MACHINE_ARM64EC),
             0x1c4 => Ok(Self::IMAGE_FILE_MACHINE_ARMNT),
             0xebc => Ok(Self::IMAGE_FILE_MACHINE_EBC),
             0x14c => Ok(Self::IMAGE_FILE_MACHINE_I386),
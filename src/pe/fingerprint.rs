@@ -0,0 +1,120 @@
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+use crate::util::to_hex;
+
+use super::{
+    import_table::{ImportLookupTableEntry, ImportTable},
+    ordinal_names::ordinal_name,
+    section_table::SectionHeader,
+};
+
+/// The import hash as computed by pefile, the MD5 of "dll.function" for every import in
+/// order, lowercased, with the common DLL extensions dropped
+pub fn imphash(import_table: &ImportTable) -> String {
+    let imports = import_table
+        .image_descriptors
+        .iter()
+        .flat_map(|descriptor| {
+            let dll = descriptor.name.to_lowercase();
+            let library = match dll.rsplit_once('.') {
+                Some((library, "dll" | "ocx" | "sys")) => library.to_string(),
+                _ => dll.clone(),
+            };
+            descriptor
+                .import_lookup_table
+                .entries
+                .iter()
+                .map(move |entry| {
+                    let function = match entry {
+                        ImportLookupTableEntry::ByName { name, .. } => {
+                            String::from_utf8_lossy(name).to_string()
+                        }
+                        ImportLookupTableEntry::ByOrdinal { ordinal, .. } => {
+                            ordinal_name(&dll, *ordinal)
+                                .map(|name| name.to_string())
+                                .unwrap_or(format!("ord{}", ordinal))
+                        }
+                    };
+                    format!("{}.{}", library, function.to_lowercase())
+                })
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+    to_hex(&Md5::digest(imports))
+}
+
+/// Shannon entropy in bits per byte, from 0 for constant data to 8 for random data
+pub fn entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for byte in data {
+        counts[*byte as usize] += 1;
+    }
+    counts
+        .iter()
+        .filter(|count| **count != 0)
+        .map(|count| {
+            let p = *count as f64 / data.len() as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[derive(Debug, Clone)]
+pub struct SectionFingerprint {
+    pub name: String,
+    pub md5: String,
+    pub sha256: String,
+    pub entropy: f64,
+}
+
+/// Hashes of the section's file data, including the alignment padding like other tools do
+pub fn fingerprint_section(section: &SectionHeader) -> SectionFingerprint {
+    SectionFingerprint {
+        name: section.name().to_string(),
+        md5: to_hex(&Md5::digest(&section.raw_data)),
+        sha256: to_hex(&Sha256::digest(&section.raw_data)),
+        entropy: entropy(&section.raw_data),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pe::{ordinal_names::ordinal_name, PortableExecutable};
+
+    use super::entropy;
+
+    #[test]
+    fn fingerprints_samples() {
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        assert_eq!(pe.imphash().unwrap(), "7a23dcee5374a4f0f6fb162ded1a923f");
+        assert_eq!(
+            pe.rich_header_hash().unwrap(),
+            "abf4248c56636f73b81e53ce7100abb6"
+        );
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        assert_eq!(pe.imphash().unwrap(), "8feeb4b8639b9197e7245e82d60db06f");
+
+        let sections = pe.section_fingerprints();
+        assert_eq!(sections.len(), pe.section_table.section_headers.len());
+        let text = &sections[0];
+        assert_eq!(text.name, ".text");
+        assert_eq!(text.md5, "a567c1915b48a63dfbb9532966039719");
+        assert_eq!(
+            text.sha256,
+            "ae84a4ecc4bab4e21e57f06a297624d6a1c86689a03ef9907448d4dd73769fe1"
+        );
+        assert!((text.entropy - 5.83117402772787).abs() < 1e-9);
+        //  .bss has no file data
+        assert_eq!(sections[5].entropy, 0.0);
+
+        assert_eq!(ordinal_name("WS2_32.dll", 115), Some("WSAStartup"));
+        assert_eq!(ordinal_name("kernel32.dll", 1), None);
+
+        assert_eq!(entropy(&[0x90; 64]), 0.0);
+        assert_eq!(entropy(&(0..=255).collect::<Vec<u8>>()), 8.0);
+    }
+}
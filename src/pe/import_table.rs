@@ -1,7 +1,5 @@
-use crate::util::{get_msb_u32, get_msb_u64, read_u8_until_null, u32_from_bytes};
-
 use super::{
-    cursor::Cursor,
+    cursor::{cstr_at, u16_at, Cursor},
     optional_header::{ExecutableKind, ImageDataDirectory},
     section_table::SectionTable,
     PeError,
@@ -10,38 +8,40 @@ use super::{
 const ORDINAL_FLAG_X64: u64 = 0x8000000000000000;
 const ORDINAL_FLAG_X86: u32 = 0x80000000;
 
-fn rva2foa(rva: u32, section_table: &SectionTable) -> u32 {
-    for section in &section_table.section_headers {
-        if rva >= section.virtual_address
-            && rva <= section.virtual_address + section.size_of_raw_data
-        {
-            return section.ptr_to_raw_data + (rva - section.virtual_address);
-        }
-    }
-    0
+/// File offset of `rva`, fails if the RVA isn't backed by file data
+pub(super) fn rva2foa(rva: u32, section_table: &SectionTable) -> Result<u32, PeError> {
+    section_table
+        .rva_to_offset(rva)
+        .ok_or(PeError::UnmappedAddress { rva })
 }
 
 /// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#import-directory-table
 pub fn get_import_table(
     section_table: &SectionTable,
-    bytes: &Vec<u8>,
+    bytes: &[u8],
     exec_kind: &ExecutableKind,
     import_table_dir: ImageDataDirectory,
 ) -> Result<ImportTable, PeError> {
-    let ul_import_foa = rva2foa(import_table_dir.virtual_address, section_table);
+    if import_table_dir.virtual_address == 0 || import_table_dir.size == 0 {
+        return Err(PeError::MissingTable(
+            "The executable doesn't have an import table".to_string(),
+        ));
+    }
+    let ul_import_foa = rva2foa(import_table_dir.virtual_address, section_table)?;
 
-    let mut cursor = Cursor::new(
-        bytes[ul_import_foa as usize..ul_import_foa as usize + import_table_dir.size as usize]
-            .to_vec(),
-    );
+    let mut cursor = Cursor::from_slice(
+        bytes,
+        ul_import_foa as usize,
+        import_table_dir.size as usize,
+    )?;
     let mut image_descriptors = vec![];
     loop {
         let mut entry = ImageImportDescriptor {
-            import_lookup_table_rva: cursor.read_u32(),
-            timedate_stamp: cursor.read_u32(),
-            forwarder_chain: cursor.read_u32(),
-            name_rva: cursor.read_u32(),
-            first_thunk: cursor.read_u32(),
+            import_lookup_table_rva: cursor.read_u32()?,
+            timedate_stamp: cursor.read_u32()?,
+            forwarder_chain: cursor.read_u32()?,
+            name_rva: cursor.read_u32()?,
+            first_thunk: cursor.read_u32()?,
 
             name: "".to_string(),
             characteristics: 0,
@@ -57,102 +57,28 @@ pub fn get_import_table(
         }
 
         let get_name = || {
-            let name_offset = rva2foa(entry.name_rva, section_table);
-            read_u8_until_null(name_offset as usize, &bytes)
+            let name_offset = rva2foa(entry.name_rva, section_table)?;
+            cstr_at(bytes, name_offset as usize)
         };
 
-        let get_characteristics = || {
-            let characteristics_offset = rva2foa(entry.import_lookup_table_rva, section_table);
-            return characteristics_offset;
-        };
+        let get_characteristics =
+            || rva2foa(entry.import_lookup_table_rva, section_table).unwrap_or(0);
 
         // FIXME: fix padding parsing
-        // Hint/Name Table
-        let get_lookup_table = || {
-            let starting_address: u32 = rva2foa(entry.first_thunk, section_table);
-            let mut cursor = Cursor::new(bytes[starting_address as usize..].to_vec());
-            let mut entries = vec![];
-            let mut entries_idx = 0;
-            match exec_kind {
-                ExecutableKind::PE32 => {
-                    loop {
-                        let data = cursor.read_u32();
-                        if data == 0 {
-                            break;
-                        }
-                        let msb = get_msb_u32(data);
-                        let is_ordinal = (msb & ORDINAL_FLAG_X86) != 0;
-                        if is_ordinal {
-                            unimplemented!();
-                        }
-                        let import_by_name_offset = data & 0x7FFFFFFF; // Mask out the MSB'
-
-                        let import_by_name_address = rva2foa(import_by_name_offset, section_table);
-                        let hint = u16::from_le_bytes([
-                            bytes[import_by_name_address as usize],
-                            bytes[import_by_name_address as usize + 1],
-                        ]);
-
-                        let func_name_bytes =
-                            read_u8_until_null(import_by_name_address as usize + 2, bytes).to_vec();
-
-                        //let fn_list_addr = rva2foa(entry.first_thunk, section_table) as usize;
-
-                        entries.push(ImportLookupTableEntry {
-                            is_ordinal,
-                            hint,
-                            name: func_name_bytes,
-                            func_ptr_address: FuncAddress::X86(
-                                entry.first_thunk as u32
-                                    + (std::mem::size_of::<u32>() * entries_idx) as u32,
-                            ),
-                        });
-                        entries_idx += 1;
-                    }
-                }
-                ExecutableKind::PE32_PLUS => {
-                    loop {
-                        let data = cursor.read_u64();
-                        if data == 0 {
-                            break;
-                        }
-                        let msb = get_msb_u64(data);
-                        let is_ordinal = (msb & ORDINAL_FLAG_X64) != 0;
-                        if is_ordinal {
-                            unimplemented!();
-                        }
-                        let import_by_name_offset = data & 0x7FFFFFFF; // Mask out the MSB'
-
-                        let import_by_name_address =
-                            rva2foa(import_by_name_offset as u32, section_table);
-                        let hint = u16::from_le_bytes([
-                            bytes[import_by_name_address as usize],
-                            bytes[import_by_name_address as usize + 1],
-                        ]);
-
-                        let func_name_bytes =
-                            read_u8_until_null(import_by_name_address as usize + 2, bytes).to_vec();
-
-                        //let fn_list_addr = rva2foa(entry.first_thunk, section_table) as usize;
-
-                        entries.push(ImportLookupTableEntry {
-                            hint,
-                            is_ordinal,
-                            name: func_name_bytes,
-                            func_ptr_address: FuncAddress::X64(
-                                entry.first_thunk as u64
-                                    + (std::mem::size_of::<u64>() * entries_idx) as u64,
-                            ),
-                        });
-                        entries_idx += 1;
-                    }
-                }
-            }
-            return entries;
+        //  the IAT is overwritten when the imports are bound, so prefer the lookup table
+        let thunks_rva = match entry.import_lookup_table_rva {
+            0 => entry.first_thunk,
+            rva => rva,
         };
-
-        entry.import_lookup_table.entries = get_lookup_table();
-        entry.name = String::from_utf8(get_name().to_vec()).unwrap();
+        entry.import_lookup_table = read_import_lookup_table(
+            section_table,
+            bytes,
+            exec_kind,
+            thunks_rva,
+            entry.first_thunk,
+            0,
+        )?;
+        entry.name = String::from_utf8_lossy(get_name()?).to_string();
 
         // FIXME: parse characteristics
         entry.characteristics = get_characteristics();
@@ -161,6 +87,76 @@ pub fn get_import_table(
     Ok(ImportTable { image_descriptors })
 }
 
+/// Walks a null terminated thunk array at `thunks_rva`, `iat_rva` is the table the loader patches.
+/// `name_base` is subtracted from hint/name pointers, it's only non zero for VA based delay imports
+pub(super) fn read_import_lookup_table(
+    section_table: &SectionTable,
+    bytes: &[u8],
+    exec_kind: &ExecutableKind,
+    thunks_rva: u32,
+    iat_rva: u32,
+    name_base: u64,
+) -> Result<ImportLookupTable, PeError> {
+    let starting_address = rva2foa(thunks_rva, section_table)? as usize;
+    let mut cursor = Cursor::from_slice(
+        bytes,
+        starting_address,
+        bytes.len().saturating_sub(starting_address),
+    )?;
+    let mut entries = vec![];
+    let mut entries_idx = 0;
+    loop {
+        let (data, is_ordinal, func_ptr_address) = match exec_kind {
+            ExecutableKind::PE32 => {
+                let data = cursor.read_u32()?;
+                (
+                    data as u64,
+                    data & ORDINAL_FLAG_X86 != 0,
+                    //  wraps like the loader does instead of panicking on crafted thunk arrays
+                    FuncAddress::X86(
+                        iat_rva.wrapping_add((std::mem::size_of::<u32>() * entries_idx) as u32),
+                    ),
+                )
+            }
+            ExecutableKind::PE32_PLUS => {
+                let data = cursor.read_u64()?;
+                (
+                    data,
+                    data & ORDINAL_FLAG_X64 != 0,
+                    FuncAddress::X64(
+                        (iat_rva as u64)
+                            .wrapping_add((std::mem::size_of::<u64>() * entries_idx) as u64),
+                    ),
+                )
+            }
+        };
+        if data == 0 {
+            break;
+        }
+        entries_idx += 1;
+
+        if is_ordinal {
+            entries.push(ImportLookupTableEntry::ByOrdinal {
+                ordinal: (data & 0xFFFF) as u16,
+                func_ptr_address,
+            });
+            continue;
+        }
+        // Hint/Name Table
+        let import_by_name_offset = (data.wrapping_sub(name_base) & 0x7FFFFFFF) as u32; // Mask out the MSB'
+        let import_by_name_address = rva2foa(import_by_name_offset, section_table)?;
+        let hint = u16_at(bytes, import_by_name_address as usize)?;
+        let func_name_bytes = cstr_at(bytes, import_by_name_address as usize + 2)?.to_vec();
+
+        entries.push(ImportLookupTableEntry::ByName {
+            hint,
+            name: func_name_bytes,
+            func_ptr_address,
+        });
+    }
+    Ok(ImportLookupTable { entries })
+}
+
 #[derive(Debug, Clone)]
 pub struct ImportTable {
     pub image_descriptors: Vec<ImageImportDescriptor>,
@@ -190,19 +186,60 @@ pub struct ImportLookupTable {
     pub entries: Vec<ImportLookupTableEntry>,
 }
 
-//  TODO: refactor this, do we really need all these fields?
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#import-lookup-table
 #[derive(Debug, Clone)]
-pub struct ImportLookupTableEntry {
-    pub hint: u16,
-    pub is_ordinal: bool,
-    pub name: Vec<u8>,
-    /// module base address + func ptr address = ptr to the function!
-    pub func_ptr_address: FuncAddress,
+pub enum ImportLookupTableEntry {
+    ByName {
+        hint: u16,
+        name: Vec<u8>,
+        /// module base address + func ptr address = ptr to the function!
+        func_ptr_address: FuncAddress,
+    },
+    ByOrdinal {
+        ordinal: u16,
+        /// module base address + func ptr address = ptr to the function!
+        func_ptr_address: FuncAddress,
+    },
 }
 
 impl ImportLookupTableEntry {
-    pub fn name(&self) -> String {
-        String::from_utf8(self.name.clone()).unwrap_or("[NAMELESS]".to_string())
+    pub fn is_ordinal(&self) -> bool {
+        matches!(self, ImportLookupTableEntry::ByOrdinal { .. })
+    }
+
+    /// `None` for imports by ordinal
+    pub fn name(&self) -> Option<String> {
+        match self {
+            ImportLookupTableEntry::ByName { name, .. } => {
+                Some(String::from_utf8_lossy(name).to_string())
+            }
+            ImportLookupTableEntry::ByOrdinal { .. } => None,
+        }
+    }
+
+    pub fn hint(&self) -> Option<u16> {
+        match self {
+            ImportLookupTableEntry::ByName { hint, .. } => Some(*hint),
+            ImportLookupTableEntry::ByOrdinal { .. } => None,
+        }
+    }
+
+    pub fn ordinal(&self) -> Option<u16> {
+        match self {
+            ImportLookupTableEntry::ByName { .. } => None,
+            ImportLookupTableEntry::ByOrdinal { ordinal, .. } => Some(*ordinal),
+        }
+    }
+
+    pub fn func_ptr_address(&self) -> &FuncAddress {
+        match self {
+            ImportLookupTableEntry::ByName {
+                func_ptr_address, ..
+            }
+            | ImportLookupTableEntry::ByOrdinal {
+                func_ptr_address, ..
+            } => func_ptr_address,
+        }
     }
 }
 
@@ -237,17 +274,114 @@ impl std::fmt::Display for ImportTable {
             writeln!(f, "characteristics: {:#x}", descriptor.characteristics)?;
             writeln!(f, "import_lookup_table:")?;
             for entry in &descriptor.import_lookup_table.entries {
-                writeln!(f, "\tis_ordinal: {}", entry.is_ordinal)?;
-                writeln!(f, "\thint: {:#x}", entry.hint)?;
-                writeln!(
-                    f,
-                    "\tname: {}",
-                    String::from_utf8(entry.name.clone()).unwrap()
-                )?;
-                writeln!(f, "\tfunc_ptr_address: {:#x?}", entry.func_ptr_address)?;
+                writeln!(f, "\tis_ordinal: {}", entry.is_ordinal())?;
+                match entry {
+                    ImportLookupTableEntry::ByName { hint, name, .. } => {
+                        writeln!(f, "\thint: {:#x}", hint)?;
+                        writeln!(f, "\tname: {}", String::from_utf8_lossy(name))?;
+                    }
+                    ImportLookupTableEntry::ByOrdinal { ordinal, .. } => {
+                        writeln!(f, "\tordinal: {}", ordinal)?;
+                    }
+                }
+                writeln!(f, "\tfunc_ptr_address: {:#x?}", entry.func_ptr_address())?;
                 writeln!(f, "-------------------")?;
             }
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::pe::{
+        optional_header::{ExecutableKind, ImageDataDirectory, ImageDirectoryEntry},
+        test_util::{put_bytes, put_u16, put_u32, section, section_table},
+        PeError, PortableExecutable,
+    };
+
+    use super::{get_import_table, FuncAddress, ImportLookupTableEntry};
+
+    #[test]
+    fn imports_by_name() {
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        let table = pe.get_import_table().unwrap();
+        let kernel32 = &table.image_descriptors[0];
+        assert_eq!(kernel32.name, "KERNEL32.dll");
+        assert_eq!(kernel32.import_lookup_table.entries.len(), 10);
+        let first = &kernel32.import_lookup_table.entries[0];
+        assert_eq!(first.name().as_deref(), Some("DeleteCriticalSection"));
+        assert_eq!(first.hint(), Some(281));
+        assert!(matches!(first.func_ptr_address(), FuncAddress::X64(0x8164)));
+
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        let table = pe.get_import_table().unwrap();
+        assert_eq!(table.image_descriptors.len(), 3);
+        assert!(table
+            .image_descriptors
+            .iter()
+            .flat_map(|d| &d.import_lookup_table.entries)
+            .all(|e| !e.is_ordinal()));
+    }
+
+    #[test]
+    fn imports_by_ordinal() {
+        let section_table = section_table([section(".idata", 0x1000, 0, 0x100)]);
+        let mut bytes = vec![0u8; 0x100];
+        //  one descriptor followed by the null descriptor
+        put_u32(&mut bytes, 0, 0x1040);
+        put_u32(&mut bytes, 12, 0x1080);
+        put_u32(&mut bytes, 16, 0x1060);
+        //  ordinal 23 then a hint/name entry
+        put_u32(&mut bytes, 0x40, 0x80000017);
+        put_u32(&mut bytes, 0x44, 0x1090);
+        put_bytes(&mut bytes, 0x80, "ws2_32.dll");
+        put_u16(&mut bytes, 0x90, 7);
+        put_bytes(&mut bytes, 0x92, "socket");
+
+        let dir = ImageDataDirectory {
+            virtual_address: 0x1000,
+            size: 40,
+            tag: "import_table".to_string(),
+        };
+        let table =
+            get_import_table(&section_table, &bytes, &ExecutableKind::PE32, dir.clone()).unwrap();
+        let ws2_32 = &table.image_descriptors[0];
+        assert_eq!(ws2_32.name, "ws2_32.dll");
+        let entries = &ws2_32.import_lookup_table.entries;
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(
+            entries[0],
+            ImportLookupTableEntry::ByOrdinal {
+                ordinal: 23,
+                func_ptr_address: FuncAddress::X86(0x1060)
+            }
+        ));
+        assert_eq!(entries[1].name().as_deref(), Some("socket"));
+        assert_eq!(entries[1].hint(), Some(7));
+        assert!(matches!(
+            entries[1].func_ptr_address(),
+            FuncAddress::X86(0x1064)
+        ));
+
+        //  an IAT at the top of the address space wraps instead of overflowing
+        put_u32(&mut bytes, 16, 0xfffffffc);
+        let table = get_import_table(&section_table, &bytes, &ExecutableKind::PE32, dir).unwrap();
+        let entries = &table.image_descriptors[0].import_lookup_table.entries;
+        assert!(matches!(entries[1].func_ptr_address(), FuncAddress::X86(0)));
+    }
+
+    #[test]
+    fn missing_import_table() {
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        let mut bytes = pe.bytes.clone();
+        let offset = pe.data_directory_offset(ImageDirectoryEntry::IMPORT);
+        bytes[offset..offset + 8].fill(0);
+        let pe = PortableExecutable::try_from(bytes).unwrap();
+        assert!(matches!(
+            pe.get_import_table(),
+            Err(PeError::MissingTable(_))
+        ));
+        assert!(matches!(pe.imphash(), Err(PeError::MissingTable(_))));
+    }
+}
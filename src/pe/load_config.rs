@@ -0,0 +1,337 @@
+use crate::util::u32_from_bytes;
+
+use super::{
+    cursor::{u32_at, Cursor},
+    import_table::rva2foa,
+    optional_header::{ExecutableKind, ImageDataDirectory},
+    section_table::SectionTable,
+    PeError,
+};
+
+/// Reads fields in order and returns `None` for the ones that fall outside of the `Size`
+/// the linker wrote, since the structure keeps growing with every Windows release
+struct VersionedReader<'a> {
+    cursor: Cursor<'a>,
+    exec_kind: &'a ExecutableKind,
+}
+
+impl<'a> VersionedReader<'a> {
+    fn fits(&mut self, bytes: usize) -> bool {
+        let fits = self.cursor.position + bytes <= self.cursor.bytes.len();
+        if !fits {
+            self.cursor.skip(bytes);
+        }
+        fits
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.fits(2).then(|| self.cursor.read_u16().ok()).flatten()
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.fits(4).then(|| self.cursor.read_u32().ok()).flatten()
+    }
+
+    /// Pointer sized fields, widened to u64 on PE32
+    fn ptr(&mut self) -> Option<u64> {
+        match self.exec_kind {
+            ExecutableKind::PE32 => self.u32().map(|v| v as u64),
+            ExecutableKind::PE32_PLUS => {
+                self.fits(8).then(|| self.cursor.read_u64().ok()).flatten()
+            }
+        }
+    }
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#load-configuration-layout
+pub fn get_load_config_directory(
+    section_table: &SectionTable,
+    bytes: &[u8],
+    exec_kind: &ExecutableKind,
+    image_base: u64,
+    load_config_dir: ImageDataDirectory,
+) -> Result<LoadConfigDirectory, PeError> {
+    if load_config_dir.virtual_address == 0 || load_config_dir.size == 0 {
+        return Err(PeError::MissingTable(
+            "The executable doesn't have a load config directory".to_string(),
+        ));
+    }
+    let load_config_foa = rva2foa(load_config_dir.virtual_address, section_table)? as usize;
+    //  the data directory size isn't reliable (it's 0x40 on most x86 images), use the Size field
+    let size = u32_at(bytes, load_config_foa)?;
+    let end = (load_config_foa + size as usize).min(bytes.len());
+    let mut r = VersionedReader {
+        cursor: Cursor::from_slice(bytes, load_config_foa, end - load_config_foa)?,
+        exec_kind,
+    };
+    r.cursor.skip(4);
+
+    let time_date_stamp = r.u32();
+    let major_version = r.u16();
+    let minor_version = r.u16();
+    let global_flags_clear = r.u32();
+    let global_flags_set = r.u32();
+    let critical_section_default_timeout = r.u32();
+    let de_commit_free_block_threshold = r.ptr();
+    let de_commit_total_free_threshold = r.ptr();
+    let lock_prefix_table = r.ptr();
+    let maximum_allocation_size = r.ptr();
+    let virtual_memory_threshold = r.ptr();
+    //  these two are swapped between IMAGE_LOAD_CONFIG_DIRECTORY32 and 64
+    let (process_heap_flags, process_affinity_mask) = match exec_kind {
+        ExecutableKind::PE32 => {
+            let flags = r.u32();
+            (flags, r.ptr())
+        }
+        ExecutableKind::PE32_PLUS => {
+            let mask = r.ptr();
+            (r.u32(), mask)
+        }
+    };
+
+    let mut directory = LoadConfigDirectory {
+        size,
+        time_date_stamp,
+        major_version,
+        minor_version,
+        global_flags_clear,
+        global_flags_set,
+        critical_section_default_timeout,
+        de_commit_free_block_threshold,
+        de_commit_total_free_threshold,
+        lock_prefix_table,
+        maximum_allocation_size,
+        virtual_memory_threshold,
+        process_affinity_mask,
+        process_heap_flags,
+        csd_version: r.u16(),
+        dependent_load_flags: r.u16(),
+        edit_list: r.ptr(),
+        security_cookie: r.ptr(),
+        se_handler_table: r.ptr(),
+        se_handler_count: r.ptr(),
+        guard_cf_check_function_pointer: r.ptr(),
+        guard_cf_dispatch_function_pointer: r.ptr(),
+        guard_cf_function_table: r.ptr(),
+        guard_cf_function_count: r.ptr(),
+        guard_flags: r.u32().map(GuardFlags::from_bits_retain),
+        code_integrity: match (r.u16(), r.u16(), r.u32(), r.u32()) {
+            (Some(flags), Some(catalog), Some(catalog_offset), Some(reserved)) => {
+                Some(LoadConfigCodeIntegrity {
+                    flags,
+                    catalog,
+                    catalog_offset,
+                    reserved,
+                })
+            }
+            _ => None,
+        },
+        guard_address_taken_iat_entry_table: r.ptr(),
+        guard_address_taken_iat_entry_count: r.ptr(),
+        guard_long_jump_target_table: r.ptr(),
+        guard_long_jump_target_count: r.ptr(),
+        dynamic_value_reloc_table: r.ptr(),
+        chpe_metadata_pointer: r.ptr(),
+        guard_rf_failure_routine: r.ptr(),
+        guard_rf_failure_routine_function_pointer: r.ptr(),
+        dynamic_value_reloc_table_offset: r.u32(),
+        dynamic_value_reloc_table_section: r.u16(),
+        reserved2: r.u16(),
+        guard_rf_verify_stack_pointer_function_pointer: r.ptr(),
+        hot_patch_table_offset: r.u32(),
+        reserved3: r.u32(),
+        enclave_configuration_pointer: r.ptr(),
+        volatile_metadata_pointer: r.ptr(),
+        guard_eh_continuation_table: r.ptr(),
+        guard_eh_continuation_count: r.ptr(),
+        guard_xfg_check_function_pointer: r.ptr(),
+        guard_xfg_dispatch_function_pointer: r.ptr(),
+        guard_xfg_table_dispatch_function_pointer: r.ptr(),
+        cast_guard_os_determined_failure_mode: r.ptr(),
+        guard_memcpy_function_pointer: r.ptr(),
+        se_handlers: vec![],
+        guard_cf_functions: vec![],
+    };
+
+    //  both tables are arrays of RVAs, the CFG one may carry extra metadata bytes per entry
+    let read_rva_table = |table_va: Option<u64>, count: Option<u64>, stride: usize| {
+        let (Some(table_va), Some(count)) = (table_va, count) else {
+            return vec![];
+        };
+        if table_va == 0 {
+            return vec![];
+        }
+        let Ok(table_foa) = rva2foa(table_va.wrapping_sub(image_base) as u32, section_table) else {
+            return vec![];
+        };
+        let table_foa = table_foa as usize;
+        (0..count as usize)
+            .map(|i| table_foa + i * stride)
+            .take_while(|offset| offset + 4 <= bytes.len())
+            .map(|offset| u32_from_bytes(&bytes[offset..]))
+            .collect::<Vec<u32>>()
+    };
+    directory.se_handlers =
+        read_rva_table(directory.se_handler_table, directory.se_handler_count, 4);
+    let cf_stride = 4 + directory
+        .guard_flags
+        .map(|flags| flags.function_table_entry_extra_bytes())
+        .unwrap_or(0);
+    directory.guard_cf_functions = read_rva_table(
+        directory.guard_cf_function_table,
+        directory.guard_cf_function_count,
+        cf_stride,
+    );
+    Ok(directory)
+}
+
+/// Aka IMAGE_LOAD_CONFIG_DIRECTORY32/IMAGE_LOAD_CONFIG_DIRECTORY64.
+/// Pointer sized fields are VAs widened to u64, fields past `size` are `None`.
+#[derive(Debug, Clone)]
+pub struct LoadConfigDirectory {
+    pub size: u32,
+    pub time_date_stamp: Option<u32>,
+    pub major_version: Option<u16>,
+    pub minor_version: Option<u16>,
+    pub global_flags_clear: Option<u32>,
+    pub global_flags_set: Option<u32>,
+    pub critical_section_default_timeout: Option<u32>,
+    pub de_commit_free_block_threshold: Option<u64>,
+    pub de_commit_total_free_threshold: Option<u64>,
+    pub lock_prefix_table: Option<u64>,
+    pub maximum_allocation_size: Option<u64>,
+    pub virtual_memory_threshold: Option<u64>,
+    pub process_affinity_mask: Option<u64>,
+    pub process_heap_flags: Option<u32>,
+    pub csd_version: Option<u16>,
+    pub dependent_load_flags: Option<u16>,
+    pub edit_list: Option<u64>,
+    pub security_cookie: Option<u64>,
+    pub se_handler_table: Option<u64>,
+    pub se_handler_count: Option<u64>,
+    pub guard_cf_check_function_pointer: Option<u64>,
+    pub guard_cf_dispatch_function_pointer: Option<u64>,
+    pub guard_cf_function_table: Option<u64>,
+    pub guard_cf_function_count: Option<u64>,
+    pub guard_flags: Option<GuardFlags>,
+    pub code_integrity: Option<LoadConfigCodeIntegrity>,
+    pub guard_address_taken_iat_entry_table: Option<u64>,
+    pub guard_address_taken_iat_entry_count: Option<u64>,
+    pub guard_long_jump_target_table: Option<u64>,
+    pub guard_long_jump_target_count: Option<u64>,
+    pub dynamic_value_reloc_table: Option<u64>,
+    pub chpe_metadata_pointer: Option<u64>,
+    pub guard_rf_failure_routine: Option<u64>,
+    pub guard_rf_failure_routine_function_pointer: Option<u64>,
+    pub dynamic_value_reloc_table_offset: Option<u32>,
+    pub dynamic_value_reloc_table_section: Option<u16>,
+    pub reserved2: Option<u16>,
+    pub guard_rf_verify_stack_pointer_function_pointer: Option<u64>,
+    pub hot_patch_table_offset: Option<u32>,
+    pub reserved3: Option<u32>,
+    pub enclave_configuration_pointer: Option<u64>,
+    pub volatile_metadata_pointer: Option<u64>,
+    pub guard_eh_continuation_table: Option<u64>,
+    pub guard_eh_continuation_count: Option<u64>,
+    pub guard_xfg_check_function_pointer: Option<u64>,
+    pub guard_xfg_dispatch_function_pointer: Option<u64>,
+    pub guard_xfg_table_dispatch_function_pointer: Option<u64>,
+    pub cast_guard_os_determined_failure_mode: Option<u64>,
+    pub guard_memcpy_function_pointer: Option<u64>,
+
+    ///  not in MS docs, RVAs of the safe exception handlers (x86 only)
+    pub se_handlers: Vec<u32>,
+    ///  not in MS docs, RVAs of the valid indirect call targets
+    pub guard_cf_functions: Vec<u32>,
+}
+
+/// Aka IMAGE_LOAD_CONFIG_CODE_INTEGRITY
+#[derive(Debug, Clone)]
+pub struct LoadConfigCodeIntegrity {
+    pub flags: u16,
+    pub catalog: u16,
+    pub catalog_offset: u32,
+    pub reserved: u32,
+}
+
+bitflags::bitflags! {
+    /// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#load-configuration-layout
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct GuardFlags: u32 {
+        const IMAGE_GUARD_CF_INSTRUMENTED = 0x00000100;
+        const IMAGE_GUARD_CFW_INSTRUMENTED = 0x00000200;
+        const IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT = 0x00000400;
+        const IMAGE_GUARD_SECURITY_COOKIE_UNUSED = 0x00000800;
+        const IMAGE_GUARD_PROTECT_DELAYLOAD_IAT = 0x00001000;
+        const IMAGE_GUARD_DELAYLOAD_IAT_IN_ITS_OWN_SECTION = 0x00002000;
+        const IMAGE_GUARD_CF_EXPORT_SUPPRESSION_INFO_PRESENT = 0x00004000;
+        const IMAGE_GUARD_CF_ENABLE_EXPORT_SUPPRESSION = 0x00008000;
+        const IMAGE_GUARD_CF_LONGJUMP_TABLE_PRESENT = 0x00010000;
+        const IMAGE_GUARD_RF_INSTRUMENTED = 0x00020000;
+        const IMAGE_GUARD_RF_ENABLE = 0x00040000;
+        const IMAGE_GUARD_RF_STRICT = 0x00080000;
+        const IMAGE_GUARD_RETPOLINE_PRESENT = 0x00100000;
+        const IMAGE_GUARD_EH_CONTINUATION_TABLE_PRESENT = 0x00400000;
+        const IMAGE_GUARD_XFG_ENABLED = 0x00800000;
+        const IMAGE_GUARD_CASTGUARD_PRESENT = 0x01000000;
+        const IMAGE_GUARD_MEMCPY_PRESENT = 0x02000000;
+        const IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_MASK = 0xF0000000;
+    }
+}
+
+impl GuardFlags {
+    /// Number of metadata bytes that follow every RVA in the GuardCFFunctionTable
+    pub fn function_table_entry_extra_bytes(&self) -> usize {
+        ((self.bits() & Self::IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_MASK.bits()) >> 28) as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pe::{PeError, PortableExecutable};
+
+    use super::GuardFlags;
+
+    #[test]
+    fn parses_load_config_x86() {
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        let load_config = pe.load_config_directory().unwrap();
+        assert_eq!(load_config.size, 0xc0);
+        assert_eq!(load_config.security_cookie, Some(0x41a024));
+        assert_eq!(load_config.se_handler_count, Some(0));
+        assert!(load_config.se_handlers.is_empty());
+        assert_eq!(load_config.guard_cf_check_function_pointer, Some(0x41d000));
+        assert_eq!(
+            load_config.guard_flags,
+            Some(GuardFlags::IMAGE_GUARD_CF_INSTRUMENTED)
+        );
+        assert_eq!(load_config.dynamic_value_reloc_table, Some(0));
+        assert_eq!(load_config.chpe_metadata_pointer, Some(0));
+        assert_eq!(
+            load_config.cast_guard_os_determined_failure_mode,
+            Some(0x41d008)
+        );
+        assert_eq!(load_config.guard_memcpy_function_pointer, Some(0));
+
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        assert!(matches!(
+            pe.load_config_directory(),
+            Err(PeError::MissingTable(_))
+        ));
+    }
+
+    #[test]
+    fn fields_past_size_are_none() {
+        let mut bytes = std::fs::read("sample_executable_x86.exe").unwrap();
+        //  shrink it to the Windows XP layout, which ends at SEHandlerCount
+        bytes[0x6ef0..0x6ef4].copy_from_slice(&0x48u32.to_le_bytes());
+        let pe = PortableExecutable::try_from(bytes).unwrap();
+        let load_config = pe.load_config_directory().unwrap();
+        assert_eq!(load_config.size, 0x48);
+        assert_eq!(load_config.security_cookie, Some(0x41a024));
+        assert_eq!(load_config.se_handler_count, Some(0));
+        assert_eq!(load_config.guard_cf_check_function_pointer, None);
+        assert_eq!(load_config.guard_flags, None);
+        assert!(load_config.code_integrity.is_none());
+    }
+}
@@ -0,0 +1,227 @@
+use super::{
+    base_relocation::apply_base_relocations,
+    file_header::Characteristic,
+    import_table::{FuncAddress, ImportLookupTableEntry},
+    PeError, PortableExecutable,
+};
+
+/// Resolves an import to the address the loader would write in the IAT,
+/// gets the DLL name as written in the import descriptor
+pub type ImportResolver<'a> = dyn FnMut(&str, &ImportLookupTableEntry) -> Option<u64> + 'a;
+
+#[derive(Default)]
+pub struct MapOptions<'a> {
+    /// Where the image is loaded, the base relocations are applied if it isn't the preferred one
+    pub image_base: Option<u64>,
+    /// Fills the IAT, it's left as in the file otherwise
+    pub resolver: Option<Box<ImportResolver<'a>>>,
+}
+
+impl<'a> MapOptions<'a> {
+    pub fn image_base(mut self, image_base: u64) -> Self {
+        self.image_base = Some(image_base);
+        self
+    }
+
+    pub fn resolver(
+        mut self,
+        resolver: impl FnMut(&str, &ImportLookupTableEntry) -> Option<u64> + 'a,
+    ) -> Self {
+        self.resolver = Some(Box::new(resolver));
+        self
+    }
+}
+
+/// Lays the file out the way the loader maps it: a SizeOfImage buffer with the headers at 0
+/// and every section at its RVA, anything without file data is zero filled
+pub fn map_image(pe: &PortableExecutable, options: MapOptions) -> Result<Vec<u8>, PeError> {
+    let win = &pe.nt_headers.opt_header.win_specific_fields;
+    let mut image = vec![0u8; win.size_of_image as usize];
+
+    let headers_size = (win.size_of_headers as usize)
+        .min(pe.bytes.len())
+        .min(image.len());
+    image[..headers_size].copy_from_slice(&pe.bytes[..headers_size]);
+
+    for section in &pe.section_table.section_headers {
+        //  raw data past the virtual size is file alignment padding
+        let size = match section.virtual_size {
+            0 => section.size_of_raw_data,
+            virtual_size => section.size_of_raw_data.min(virtual_size),
+        } as usize;
+        let data = section.raw_data.get(..size).unwrap_or(&section.raw_data);
+        let start = section.virtual_address as usize;
+        image
+            .get_mut(start..start + data.len())
+            .ok_or(PeError::OutOfBounds {
+                offset: start,
+                size: data.len(),
+            })?
+            .copy_from_slice(data);
+    }
+
+    if let Some(image_base) = options.image_base {
+        relocate(pe, &mut image, image_base)?;
+    }
+    if let Some(mut resolver) = options.resolver {
+        resolve_imports(pe, &mut image, &mut resolver)?;
+    }
+    Ok(image)
+}
+
+fn relocate(pe: &PortableExecutable, image: &mut [u8], image_base: u64) -> Result<(), PeError> {
+    let delta = image_base.wrapping_sub(
+        pe.nt_headers
+            .opt_header
+            .win_specific_fields
+            .image_base
+            .value(),
+    );
+    if delta == 0 {
+        return Ok(());
+    }
+    let characteristics = &pe.nt_headers.file_header.characteristics;
+    if characteristics.contains(Characteristic::IMAGE_FILE_RELOCS_STRIPPED) {
+        return Err(PeError::ParseError(format!(
+            "The image can't be loaded at {:#x}, its relocations are stripped",
+            image_base
+        )));
+    }
+    pe.write_image_base(image, image_base)?;
+    let relocations = pe.base_relocations()?;
+    apply_base_relocations(image, &relocations, delta, |rva| Ok(rva as usize))
+}
+
+fn resolve_imports(
+    pe: &PortableExecutable,
+    image: &mut [u8],
+    resolver: &mut ImportResolver,
+) -> Result<(), PeError> {
+    let import_table = match pe.get_import_table() {
+        Ok(import_table) => import_table,
+        Err(PeError::MissingTable(_)) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    for descriptor in &import_table.image_descriptors {
+        for entry in &descriptor.import_lookup_table.entries {
+            let address = resolver(&descriptor.name, entry).ok_or_else(|| {
+                let import = match (entry.name(), entry.ordinal()) {
+                    (Some(name), _) => name,
+                    (_, Some(ordinal)) => format!("#{}", ordinal),
+                    _ => unreachable!(),
+                };
+                PeError::ParseError(format!("Could not resolve {}!{}", descriptor.name, import))
+            })?;
+            let (slot, value) = match entry.func_ptr_address() {
+                FuncAddress::X86(rva) => (*rva as usize, (address as u32).to_le_bytes().to_vec()),
+                FuncAddress::X64(rva) => (*rva as usize, address.to_le_bytes().to_vec()),
+            };
+            image
+                .get_mut(slot..slot + value.len())
+                .ok_or(PeError::OutOfBounds {
+                    offset: slot,
+                    size: value.len(),
+                })?
+                .copy_from_slice(&value);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        pe::{optional_header::ImageDirectoryEntry, PortableExecutable},
+        util::u32_from_bytes,
+    };
+
+    use super::MapOptions;
+
+    #[test]
+    fn maps_sections_at_their_rva() {
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        let image = pe.map_image(MapOptions::default()).unwrap();
+        assert_eq!(image.len(), 0x14000);
+        assert_eq!(image[..0x400], pe.bytes[..0x400]);
+
+        for section in &pe.section_table.section_headers {
+            let start = section.virtual_address as usize;
+            let size = section.virtual_size.min(section.size_of_raw_data) as usize;
+            assert_eq!(image[start..start + size], section.raw_data[..size]);
+            //  zero filled past the file data
+            let end = start + (section.virtual_size as usize).next_multiple_of(0x1000);
+            assert!(image[start + size..end].iter().all(|b| *b == 0));
+        }
+    }
+
+    #[test]
+    fn relocates_and_fills_the_iat() {
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        let rebased = PortableExecutable::try_from(pe.rebase(0x20000000).unwrap()).unwrap();
+
+        let mut resolved = vec![];
+        let image = pe
+            .map_image(
+                MapOptions::default()
+                    .image_base(0x20000000)
+                    .resolver(|dll, entry| {
+                        resolved.push(format!("{}!{}", dll, entry.name().unwrap()));
+                        Some(0x77000000 + resolved.len() as u64)
+                    }),
+            )
+            .unwrap();
+
+        //  relocations match the file based rebase
+        let image_without_iat = pe
+            .map_image(MapOptions::default().image_base(0x20000000))
+            .unwrap();
+        let text = rebased.section_table.get_section_header(".text").unwrap();
+        let start = text.virtual_address as usize;
+        let size = text.virtual_size as usize;
+        assert_eq!(
+            image_without_iat[start..start + size],
+            text.raw_data[..size]
+        );
+        let image_base_offset = pe.opt_header_offset() + 28;
+        assert_eq!(u32_from_bytes(&image[image_base_offset..]), 0x20000000);
+
+        let imports = pe.get_import_table().unwrap();
+        let first = &imports.image_descriptors[0];
+        assert_eq!(
+            resolved[0],
+            format!(
+                "{}!{}",
+                first.name,
+                first.import_lookup_table.entries[0].name().unwrap()
+            )
+        );
+        let iat = first.first_thunk as usize;
+        assert_eq!(u32_from_bytes(&image[iat..]), 0x77000001);
+        assert_eq!(u32_from_bytes(&image[iat + 4..]), 0x77000002);
+
+        //  unresolved imports are an error
+        assert!(pe
+            .map_image(MapOptions::default().resolver(|_, _| None))
+            .is_err());
+    }
+
+    #[test]
+    fn maps_an_image_without_imports() {
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        let mut bytes = pe.bytes.clone();
+        let offset = pe.data_directory_offset(ImageDirectoryEntry::IMPORT);
+        bytes[offset..offset + 8].fill(0);
+        let pe = PortableExecutable::try_from(bytes).unwrap();
+
+        //  the resolver is never called but mapping still succeeds
+        let mut called = false;
+        let image = pe
+            .map_image(MapOptions::default().resolver(|_, _| {
+                called = true;
+                None
+            }))
+            .unwrap();
+        assert!(!called);
+        assert_eq!(image.len(), 0x14000);
+    }
+}
@@ -1,21 +1,65 @@
+pub mod archive;
+pub mod base_relocation;
+pub mod builder;
+pub mod certificate_table;
+pub mod checksum;
+pub mod coff;
 pub mod cursor;
+pub mod debug_table;
+pub mod delay_import_table;
+pub mod dos_header;
+pub mod exception_table;
+pub mod export_table;
 pub mod file_header;
+pub mod fingerprint;
 pub mod import_table;
+pub mod load_config;
+pub mod loader;
 pub mod optional_header;
+pub mod ordinal_names;
+pub mod resource_table;
+pub mod rich_header;
 pub mod section_table;
+pub mod symbol_table;
+#[cfg(test)]
+mod test_util;
+pub mod tls_table;
+pub mod view;
 
+use std::ops::Range;
+
+use sha2::Digest;
 use thiserror::Error;
-use windows::Win32::System::Diagnostics::Debug::{
-    IMAGE_DIRECTORY_ENTRY, IMAGE_DIRECTORY_ENTRY_IMPORT,
-};
+
+use crate::util::to_hex;
 
 use self::{
-    import_table::{get_import_table, ImportTable},
-    optional_header::{ExecutableKind, ImageDataDirectory},
+    base_relocation::{apply_base_relocations, get_base_relocations, BaseRelocationTable},
+    builder::PeBuilder,
+    certificate_table::{
+        authenticode_hash, get_certificate_table, CertificateTable, DigestAlgorithm,
+    },
+    checksum::{pe_checksum, CHECKSUM_OFFSET},
+    cursor::slice_at,
+    debug_table::{get_debug_directory, CodeViewInfo, DebugData, DebugDirectoryEntry},
+    delay_import_table::{get_delay_import_table, DelayImportTable},
+    exception_table::{get_exception_table, ExceptionTable},
+    export_table::{get_export_table, ExportTable},
+    fingerprint::{fingerprint_section, imphash, SectionFingerprint},
+    import_table::{get_import_table, rva2foa, ImportTable},
+    load_config::{get_load_config_directory, LoadConfigDirectory},
+    loader::{map_image, MapOptions},
+    optional_header::{ExecutableKind, ImageBase, ImageDataDirectory, ImageDirectoryEntry},
+    resource_table::{get_resource_table, ResourceTable},
+    rich_header::{get_rich_header, RichHeader},
+    symbol_table::{get_string_table, SymbolTable},
+    tls_table::{get_tls_directory, TlsDirectory},
+    view::PeView,
 };
 
 #[derive(Clone)]
 pub struct PortableExecutable {
+    pub dos_header: dos_header::DosHeader,
     pub nt_headers: NtHeaders,
     pub section_table: section_table::SectionTable,
     pub executable_type: ExecutableKind,
@@ -50,9 +94,16 @@ pub enum PeError {
     MissingSection(String),
     #[error("Missing Table: {0}")]
     MissingTable(String),
+    #[error("Out of bounds: tried to read {size} bytes at offset {offset:#x}")]
+    OutOfBounds { offset: usize, size: usize },
+    #[error("Invalid string at offset {offset:#x}")]
+    InvalidString { offset: usize },
+    #[error("RVA {rva:#x} isn't backed by file data")]
+    UnmappedAddress { rva: u32 },
+    #[error("Checksum mismatch: the header says {stored:#x}, the file sums to {computed:#x}")]
+    ChecksumMismatch { stored: u32, computed: u32 },
 }
 
-//  TODO: parse the string tables
 impl PortableExecutable {
     pub fn from_file(path: impl Into<String>) -> Result<PortableExecutable, PeError> {
         let file = std::fs::read(path.into())?;
@@ -60,56 +111,389 @@ impl PortableExecutable {
     }
 
     fn parse(bytes: impl Into<Vec<u8>>) -> Result<Self, PeError> {
-        let mut cursor = cursor::Cursor::new(bytes.into());
-        let mz = cursor.read_u16();
-
-        let mut pe_signature = cursor.read_u16();
-        let max_offset = cursor.bytes.len() - 2;
-        while pe_signature != 0x4550 {
-            if cursor.position >= max_offset {
-                return Err(PeError::ParseError(
-                    "Could not find the PE signature".to_string(),
-                ));
-            }
-            pe_signature = cursor.read_u16();
+        let bytes = bytes.into();
+        let PeView {
+            dos_header,
+            nt_headers,
+            mut section_table,
+            executable_type,
+            ..
+        } = PeView::parse(&bytes)?;
+        for section in &mut section_table.section_headers {
+            section.raw_data = slice_at(
+                &bytes,
+                section.ptr_to_raw_data as usize,
+                section.size_of_raw_data as usize,
+            )?
+            .to_vec();
         }
-
-        //  skip the 2 null bytes
-        cursor.skip(2);
-
-        let file_header = file_header::parse_file_header(&mut cursor)?;
-
-        //  FIXME: skip is optional, do i even check if it's there or not?
-        let opt_header = optional_header::parse_opt_header(&mut cursor)?;
-        let magic = opt_header.std_fields.magic.clone();
-        let section_table =
-            section_table::parse_section_headers(&mut cursor, file_header.number_of_sections)?;
-
-        let nt_headers = NtHeaders {
-            pe_signature,
-            file_header,
-            opt_header,
-        };
         Ok(PortableExecutable {
+            dos_header,
             nt_headers,
-            executable_type: magic,
+            executable_type,
             section_table,
-            bytes: cursor.bytes,
+            bytes,
         })
     }
 
+    /// The COFF symbol table, MinGW keeps it in unstripped images
+    pub fn symbol_table(&self) -> Result<SymbolTable, PeError> {
+        self.nt_headers.file_header.symbol_table(&self.bytes)
+    }
+
+    /// The section's name, MinGW writes names longer than 8 bytes, e.g. the DWARF sections,
+    /// to the string table like in objects
+    pub fn section_name(&self, section: &section_table::SectionHeader) -> String {
+        let file_header = &self.nt_headers.file_header;
+        get_string_table(
+            &self.bytes,
+            file_header.ptr_to_symbol_table,
+            file_header.number_of_symbols,
+        )
+        .and_then(|string_table| string_table.section_name(&section.name))
+        .unwrap_or_else(|_| section.name().to_string())
+    }
+
+    pub fn rich_header(&self) -> Result<RichHeader, PeError> {
+        get_rich_header(&self.bytes, self.dos_header.e_lfanew)
+    }
+
+    /// MD5 of the decoded Rich header, tells apart builds from different toolchains
+    pub fn rich_header_hash(&self) -> Result<String, PeError> {
+        let rich_header = self.rich_header()?;
+        Ok(to_hex(&md5::Md5::digest(rich_header.decoded(&self.bytes))))
+    }
+
+    pub fn imphash(&self) -> Result<String, PeError> {
+        Ok(imphash(&self.get_import_table()?))
+    }
+
+    pub fn section_fingerprints(&self) -> Vec<SectionFingerprint> {
+        self.section_table
+            .section_headers
+            .iter()
+            .map(fingerprint_section)
+            .collect()
+    }
+
     pub fn get_import_table(&self) -> Result<ImportTable, PeError> {
         get_import_table(
             &self.section_table,
             &self.bytes,
             &self.executable_type,
-            self.nt_headers.opt_header.data_directories[IMAGE_DIRECTORY_ENTRY_IMPORT.0 as usize]
+            self.nt_headers.opt_header.data_directories[ImageDirectoryEntry::IMPORT as usize]
+                .clone(),
+        )
+    }
+
+    pub fn delay_import_table(&self) -> Result<DelayImportTable, PeError> {
+        get_delay_import_table(
+            &self.section_table,
+            &self.bytes,
+            &self.executable_type,
+            self.nt_headers
+                .opt_header
+                .win_specific_fields
+                .image_base
+                .value(),
+            self.get_image_directory(ImageDirectoryEntry::DELAY_IMPORT),
+        )
+    }
+
+    pub fn get_export_table(&self) -> Result<ExportTable, PeError> {
+        get_export_table(
+            &self.section_table,
+            &self.bytes,
+            self.nt_headers.opt_header.data_directories[ImageDirectoryEntry::EXPORT as usize]
                 .clone(),
         )
     }
 
-    pub fn get_image_directory(&self, entry: IMAGE_DIRECTORY_ENTRY) -> ImageDataDirectory {
-        self.nt_headers.opt_header.data_directories[entry.0 as usize].clone()
+    pub fn resource_table(&self) -> Result<ResourceTable, PeError> {
+        get_resource_table(
+            &self.section_table,
+            &self.bytes,
+            self.nt_headers.opt_header.data_directories[ImageDirectoryEntry::RESOURCE as usize]
+                .clone(),
+        )
+    }
+
+    pub fn tls_directory(&self) -> Result<TlsDirectory, PeError> {
+        get_tls_directory(
+            &self.section_table,
+            &self.bytes,
+            &self.executable_type,
+            self.nt_headers
+                .opt_header
+                .win_specific_fields
+                .image_base
+                .value(),
+            self.nt_headers.opt_header.data_directories[ImageDirectoryEntry::TLS as usize].clone(),
+        )
+    }
+
+    pub fn load_config_directory(&self) -> Result<LoadConfigDirectory, PeError> {
+        get_load_config_directory(
+            &self.section_table,
+            &self.bytes,
+            &self.executable_type,
+            self.nt_headers
+                .opt_header
+                .win_specific_fields
+                .image_base
+                .value(),
+            self.get_image_directory(ImageDirectoryEntry::LOAD_CONFIG),
+        )
+    }
+
+    pub fn debug_directory(&self) -> Result<Vec<DebugDirectoryEntry>, PeError> {
+        get_debug_directory(
+            &self.section_table,
+            &self.bytes,
+            self.get_image_directory(ImageDirectoryEntry::DEBUG),
+        )
+    }
+
+    /// The CodeView record that points to the PDB, if the executable has one
+    pub fn codeview(&self) -> Result<Option<CodeViewInfo>, PeError> {
+        Ok(self
+            .debug_directory()?
+            .into_iter()
+            .find_map(|entry| match entry.data {
+                DebugData::CodeView(info) => Some(info),
+                _ => None,
+            }))
+    }
+
+    pub fn exception_table(&self) -> Result<ExceptionTable, PeError> {
+        get_exception_table(
+            &self.section_table,
+            &self.bytes,
+            &self.nt_headers.file_header.machine,
+            self.get_image_directory(ImageDirectoryEntry::EXCEPTION),
+        )
+    }
+
+    pub fn base_relocations(&self) -> Result<BaseRelocationTable, PeError> {
+        get_base_relocations(
+            &self.section_table,
+            &self.bytes,
+            self.nt_headers.opt_header.data_directories[ImageDirectoryEntry::BASERELOC as usize]
+                .clone(),
+        )
+    }
+
+    /// Returns a copy of the file with the base relocations applied as if it was loaded at
+    /// `new_image_base`, and with the ImageBase field updated to match
+    pub fn rebase(&self, new_image_base: u64) -> Result<Vec<u8>, PeError> {
+        let relocations = self.base_relocations()?;
+        let mut bytes = self.bytes.clone();
+        let image_base = &self.nt_headers.opt_header.win_specific_fields.image_base;
+        let delta = new_image_base.wrapping_sub(image_base.value());
+
+        self.write_image_base(&mut bytes, new_image_base)?;
+
+        apply_base_relocations(&mut bytes, &relocations, delta, |rva| {
+            Ok(rva2foa(rva, &self.section_table)? as usize)
+        })?;
+        Ok(bytes)
+    }
+
+    /// The image as the loader maps it, see [`MapOptions`] to relocate it and fill the IAT
+    pub fn map_image(&self, options: MapOptions) -> Result<Vec<u8>, PeError> {
+        map_image(self, options)
+    }
+
+    /// The image checksum as computed by CheckSumMappedFile
+    pub fn compute_checksum(&self) -> u32 {
+        pe_checksum(&self.bytes, self.opt_header_offset() + CHECKSUM_OFFSET)
+    }
+
+    /// Fails if the CheckSum field doesn't match the file, a zero checksum is a mismatch too
+    pub fn verify_checksum(&self) -> Result<(), PeError> {
+        let stored = self.nt_headers.opt_header.win_specific_fields.checksum;
+        let computed = self.compute_checksum();
+        if stored != computed {
+            return Err(PeError::ChecksumMismatch { stored, computed });
+        }
+        Ok(())
+    }
+
+    pub fn certificate_table(&self) -> Result<CertificateTable, PeError> {
+        get_certificate_table(
+            &self.bytes,
+            self.get_image_directory(ImageDirectoryEntry::SECURITY),
+        )
+    }
+
+    /// The Authenticode hash of the image, what a signature over this file would have signed
+    pub fn authenticode_hash(&self, algorithm: &DigestAlgorithm) -> Result<Vec<u8>, PeError> {
+        authenticode_hash(
+            &self.bytes,
+            self.opt_header_offset() + CHECKSUM_OFFSET,
+            self.data_directory_offset(ImageDirectoryEntry::SECURITY),
+            &self.get_image_directory(ImageDirectoryEntry::SECURITY),
+            algorithm,
+        )
+    }
+
+    /// Whether the file still hashes to the digest its signature signed.
+    /// The certificate chain and the signature itself aren't checked
+    pub fn verify_authenticode(&self) -> Result<bool, PeError> {
+        let signature = self.certificate_table()?.signature()?;
+        Ok(self.authenticode_hash(&signature.digest_algorithm)? == signature.digest)
+    }
+
+    /// The data appended after the last section, e.g. an installer payload or MinGW's symbols.
+    /// The certificate table isn't part of it even though it lives past the sections too
+    pub fn overlay(&self) -> Option<Range<usize>> {
+        let mut overlay = self.sections_end()..self.bytes.len();
+        let certificates = self.get_image_directory(ImageDirectoryEntry::SECURITY);
+        if certificates.size != 0 {
+            let start = certificates.virtual_address as usize;
+            let end = start.saturating_add(certificates.size as usize);
+            if overlay.contains(&start) {
+                overlay.end = start;
+            } else if start <= overlay.start && end > overlay.start {
+                overlay.start = end.min(overlay.end);
+            }
+        }
+        (!overlay.is_empty()).then_some(overlay)
+    }
+
+    pub fn overlay_data(&self) -> &[u8] {
+        self.overlay().map_or(&[], |overlay| &self.bytes[overlay])
+    }
+
+    pub fn write_overlay(&self, path: impl AsRef<std::path::Path>) -> Result<(), PeError> {
+        Ok(std::fs::write(path, self.overlay_data())?)
+    }
+
+    /// Returns a copy of the file without the overlay. A certificate table after it is kept
+    /// and moved, a COFF symbol table inside it is dropped, and the checksum is updated
+    pub fn strip_overlay(&self) -> Vec<u8> {
+        let Some(overlay) = self.overlay() else {
+            return self.bytes.clone();
+        };
+        let mut bytes = self.bytes.clone();
+        bytes.drain(overlay.clone());
+
+        let certificates_offset = self.data_directory_offset(ImageDirectoryEntry::SECURITY);
+        let certificates = self.get_image_directory(ImageDirectoryEntry::SECURITY);
+        if certificates.size != 0 && certificates.virtual_address as usize >= overlay.end {
+            let moved = certificates.virtual_address - overlay.len() as u32;
+            bytes[certificates_offset..certificates_offset + 4]
+                .copy_from_slice(&moved.to_le_bytes());
+        }
+        let file_header = &self.nt_headers.file_header;
+        if overlay.contains(&(file_header.ptr_to_symbol_table as usize)) {
+            let symbols_offset = self.dos_header.e_lfanew as usize + 4 + 8;
+            bytes[symbols_offset..symbols_offset + 8].fill(0);
+        }
+
+        let checksum_offset = self.opt_header_offset() + CHECKSUM_OFFSET;
+        let checksum = pe_checksum(&bytes, checksum_offset);
+        bytes[checksum_offset..checksum_offset + 4].copy_from_slice(&checksum.to_le_bytes());
+        bytes
+    }
+
+    /// File offset where the section data ends
+    fn sections_end(&self) -> usize {
+        self.section_table
+            .section_headers
+            .iter()
+            .map(|section| section.ptr_to_raw_data as usize + section.size_of_raw_data as usize)
+            .max()
+            .unwrap_or(
+                self.nt_headers
+                    .opt_header
+                    .win_specific_fields
+                    .size_of_headers as usize,
+            )
+            .min(self.bytes.len())
+    }
+
+    /// File offset of `rva`, `None` if the RVA isn't backed by data in this file
+    pub fn rva_to_offset(&self, rva: u32) -> Option<usize> {
+        self.section_table
+            .rva_to_offset(rva)
+            .map(|offset| offset as usize)
+            .filter(|offset| *offset < self.bytes.len())
+    }
+
+    /// The RVA the byte at `offset` is loaded at, `None` for data the loader doesn't map
+    pub fn offset_to_rva(&self, offset: usize) -> Option<u32> {
+        if offset >= self.bytes.len() {
+            return None;
+        }
+        self.section_table.offset_to_rva(offset.try_into().ok()?)
+    }
+
+    /// `None` if the VA is outside of the image when it's loaded at its preferred base
+    pub fn va_to_rva(&self, va: u64) -> Option<u32> {
+        let win = &self.nt_headers.opt_header.win_specific_fields;
+        va.checked_sub(win.image_base.value())
+            .and_then(|rva| u32::try_from(rva).ok())
+            .filter(|rva| *rva < win.size_of_image)
+    }
+
+    /// Wraps around like the loader's arithmetic for bases at the top of the address space
+    pub fn rva_to_va(&self, rva: u32) -> u64 {
+        self.nt_headers
+            .opt_header
+            .win_specific_fields
+            .image_base
+            .value()
+            .wrapping_add(rva as u64)
+    }
+
+    pub fn section_for_rva(&self, rva: u32) -> Option<&section_table::SectionHeader> {
+        self.section_table.section_for_rva(rva)
+    }
+
+    /// Starts editing a copy of the file, see [`PeBuilder`]
+    pub fn builder(&self) -> Result<PeBuilder, PeError> {
+        PeBuilder::new(self)
+    }
+
+    /// Patches the ImageBase field of a copy of the headers
+    fn write_image_base(&self, bytes: &mut [u8], new_image_base: u64) -> Result<(), PeError> {
+        let opt_header_offset = self.opt_header_offset();
+        match self.nt_headers.opt_header.win_specific_fields.image_base {
+            ImageBase::PE32(_) => {
+                let new_image_base = u32::try_from(new_image_base).map_err(|_| {
+                    PeError::ParseError(format!(
+                        "Image base {:#x} doesn't fit in a PE32 image",
+                        new_image_base
+                    ))
+                })?;
+                bytes[opt_header_offset + 28..opt_header_offset + 32]
+                    .copy_from_slice(&new_image_base.to_le_bytes());
+            }
+            ImageBase::PE32_PLUS(_) => {
+                bytes[opt_header_offset + 24..opt_header_offset + 32]
+                    .copy_from_slice(&new_image_base.to_le_bytes());
+            }
+        }
+        Ok(())
+    }
+
+    /// File offset of the optional header, right after the PE signature and the file header
+    fn opt_header_offset(&self) -> usize {
+        self.dos_header.e_lfanew as usize + 4 + 20
+    }
+
+    /// File offset of a data directory entry, they follow the fixed part of the optional header
+    fn data_directory_offset(&self, entry: ImageDirectoryEntry) -> usize {
+        let fixed_size = match self.executable_type {
+            ExecutableKind::PE32 => 96,
+            ExecutableKind::PE32_PLUS => 112,
+        };
+        self.opt_header_offset() + fixed_size + entry as usize * 8
+    }
+
+    pub fn get_image_directory(&self, entry: ImageDirectoryEntry) -> ImageDataDirectory {
+        self.nt_headers.opt_header.data_directories[entry as usize].clone()
     }
 }
 
@@ -140,3 +524,135 @@ impl TryFrom<String> for PortableExecutable {
         Self::parse(data)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{optional_header::ImageDirectoryEntry, PortableExecutable};
+
+    /// Runs every parser, only panics matter here
+    fn parse_everything(bytes: &[u8]) {
+        let Ok(pe) = PortableExecutable::try_from(bytes) else {
+            return;
+        };
+        let _ = pe.rich_header();
+        let _ = pe.get_import_table();
+        let _ = pe.delay_import_table();
+        let _ = pe.get_export_table();
+        let _ = pe.resource_table().map(|table| {
+            let _ = table.version_info();
+            let _ = table.manifest();
+            let _ = table.group_icons();
+        });
+        let _ = pe.exception_table();
+        let _ = pe.tls_directory();
+        let _ = pe.load_config_directory();
+        let _ = pe.debug_directory();
+        let _ = pe.base_relocations();
+        let _ = pe.rebase(0x10000);
+        let _ = pe.symbol_table();
+        for section in &pe.section_table.section_headers {
+            let _ = pe.section_name(section);
+        }
+    }
+
+    #[test]
+    fn truncated_files_dont_panic() {
+        for path in ["sample_executable.exe", "sample_executable_x86.exe"] {
+            let bytes = std::fs::read(path).unwrap();
+            //  every length through the headers, then a sample of the section data
+            for len in (0..0x400).chain((0x400..bytes.len()).step_by(61)) {
+                parse_everything(&bytes[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn corrupted_headers_dont_panic() {
+        for path in ["sample_executable.exe", "sample_executable_x86.exe"] {
+            let bytes = std::fs::read(path).unwrap();
+            for offset in 0..0x400 {
+                for value in [0x00, 0x7f, 0xff] {
+                    let mut bytes = bytes.clone();
+                    bytes[offset] = value;
+                    parse_everything(&bytes);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn finds_the_overlay() {
+        //  MinGW leaves the COFF symbol table after the sections
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        assert_eq!(pe.overlay(), Some(0x5800..46024));
+        assert_eq!(
+            pe.nt_headers.file_header.ptr_to_symbol_table,
+            pe.overlay().unwrap().start as u32
+        );
+        let stripped = PortableExecutable::try_from(pe.strip_overlay()).unwrap();
+        assert!(stripped.overlay().is_none());
+        assert_eq!(stripped.nt_headers.file_header.ptr_to_symbol_table, 0);
+        stripped.verify_checksum().unwrap();
+
+        //  a payload followed by a certificate table
+        let mut bytes = std::fs::read("sample_executable_x86.exe").unwrap();
+        let pe = PortableExecutable::try_from(bytes.clone()).unwrap();
+        assert!(pe.overlay().is_none());
+        let sections_end = bytes.len();
+        bytes.extend(b"solaire\0");
+        let certificates_offset = pe.data_directory_offset(ImageDirectoryEntry::SECURITY);
+        let table_offset = bytes.len() as u32;
+        bytes[certificates_offset..certificates_offset + 4]
+            .copy_from_slice(&table_offset.to_le_bytes());
+        bytes[certificates_offset + 4..certificates_offset + 8]
+            .copy_from_slice(&16u32.to_le_bytes());
+        bytes.extend([0xcc; 16]);
+
+        let pe = PortableExecutable::try_from(bytes).unwrap();
+        assert_eq!(pe.overlay(), Some(sections_end..sections_end + 8));
+        assert_eq!(pe.overlay_data(), b"solaire\0");
+        let stripped = PortableExecutable::try_from(pe.strip_overlay()).unwrap();
+        assert!(stripped.overlay().is_none());
+        let table = stripped.get_image_directory(ImageDirectoryEntry::SECURITY);
+        assert_eq!(table.virtual_address as usize, sections_end);
+        assert_eq!(stripped.bytes.len(), sections_end + 16);
+    }
+
+    #[test]
+    fn translates_addresses() {
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        //  headers are mapped as is
+        assert_eq!(pe.rva_to_offset(0x3c), Some(0x3c));
+        assert_eq!(pe.offset_to_rva(0x3c), Some(0x3c));
+
+        //  .text: VirtualSize 0x16b8, raw data at 0x600
+        assert_eq!(pe.rva_to_offset(0x1000), Some(0x600));
+        assert_eq!(pe.rva_to_offset(0x26b7), Some(0x600 + 0x16b7));
+        assert_eq!(pe.rva_to_offset(0x26b8), None);
+        assert_eq!(pe.offset_to_rva(0x600 + 0x16b7), Some(0x26b7));
+        //  the raw data is padded past VirtualSize, the padding isn't mapped
+        assert_eq!(pe.offset_to_rva(0x600 + 0x16b8), None);
+
+        //  .bss has no file data
+        assert_eq!(pe.section_for_rva(0x7010).unwrap().name(), ".bss");
+        assert_eq!(pe.rva_to_offset(0x7010), None);
+        assert!(pe.section_for_rva(0x7180).is_none());
+
+        assert_eq!(pe.va_to_rva(0x140001000), Some(0x1000));
+        assert_eq!(pe.va_to_rva(0x13fffffff), None);
+        assert_eq!(pe.va_to_rva(0x140000000 + 0x14000), None);
+        assert_eq!(pe.rva_to_va(0x1000), 0x140001000);
+
+        //  pointers near the top of the address space don't wrap
+        let mut section_table = pe.section_table.clone();
+        let text = &mut section_table.section_headers[0];
+        text.ptr_to_raw_data = u32::MAX - 0x10;
+        assert_eq!(section_table.rva_to_offset(0x1000), Some(u32::MAX - 0x10));
+        assert_eq!(section_table.rva_to_offset(0x1100), None);
+        let text = &mut section_table.section_headers[0];
+        text.ptr_to_raw_data = 0x600;
+        text.virtual_address = u32::MAX - 0x10;
+        assert_eq!(section_table.offset_to_rva(0x610), Some(u32::MAX));
+        assert_eq!(section_table.offset_to_rva(0x620), None);
+    }
+}
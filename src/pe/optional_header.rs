@@ -7,18 +7,18 @@ use super::{cursor::Cursor, PeError};
 
 pub fn parse_opt_header(cursor: &mut Cursor) -> Result<OptionalHeader, PeError> {
     use ExecutableKind::{PE32, PE32_PLUS};
-    let magic = ExecutableKind::try_from(cursor.read_u16())?;
-    let major_minor = &cursor.read(2);
+    let magic = ExecutableKind::try_from(cursor.read_u16()?)?;
+    let major_minor = &cursor.read(2)?;
     let std_fields = StandardFields {
         major_linker_version: major_minor[0],
         minor_linker_version: major_minor[1],
-        size_of_code: cursor.read_u32(),
-        size_of_initialized_data: cursor.read_u32(),
-        size_of_uninitialized_data: cursor.read_u32(),
-        address_of_entry_point: cursor.read_u32(),
-        base_of_code: cursor.read_u32(),
+        size_of_code: cursor.read_u32()?,
+        size_of_initialized_data: cursor.read_u32()?,
+        size_of_uninitialized_data: cursor.read_u32()?,
+        address_of_entry_point: cursor.read_u32()?,
+        base_of_code: cursor.read_u32()?,
         base_of_data: match magic {
-            PE32 => Some(cursor.read_u32()),
+            PE32 => Some(cursor.read_u32()?),
             PE32_PLUS => None,
         },
         magic,
@@ -26,24 +26,24 @@ pub fn parse_opt_header(cursor: &mut Cursor) -> Result<OptionalHeader, PeError>
 
     let win_specific_fields = WindowsSpecificFields {
         image_base: match std_fields.magic {
-            PE32 => ImageBase::PE32(cursor.read_u32()),
-            PE32_PLUS => ImageBase::PE32_PLUS(cursor.read_u64()),
+            PE32 => ImageBase::PE32(cursor.read_u32()?),
+            PE32_PLUS => ImageBase::PE32_PLUS(cursor.read_u64()?),
         },
-        section_alignment: cursor.read_u32(),
-        file_alignment: cursor.read_u32(),
-        major_os_version: cursor.read_u16(),
-        minor_os_version: cursor.read_u16(),
-        major_image_version: cursor.read_u16(),
-        minor_image_version: cursor.read_u16(),
-        major_subsystem_version: cursor.read_u16(),
-        minor_subsystem_version: cursor.read_u16(),
-        win32_version_value: cursor.read_u32(),
-        size_of_image: cursor.read_u32(),
-        size_of_headers: cursor.read_u32(),
-        checksum: cursor.read_u32(),
-        subsystem: WindowsSubsystem::try_from(cursor.read_u16())?,
-
-        dll_characteristics: format!("0x{:x}", cursor.read_u16())
+        section_alignment: cursor.read_u32()?,
+        file_alignment: cursor.read_u32()?,
+        major_os_version: cursor.read_u16()?,
+        minor_os_version: cursor.read_u16()?,
+        major_image_version: cursor.read_u16()?,
+        minor_image_version: cursor.read_u16()?,
+        major_subsystem_version: cursor.read_u16()?,
+        minor_subsystem_version: cursor.read_u16()?,
+        win32_version_value: cursor.read_u32()?,
+        size_of_image: cursor.read_u32()?,
+        size_of_headers: cursor.read_u32()?,
+        checksum: cursor.read_u32()?,
+        subsystem: WindowsSubsystem::try_from(cursor.read_u16()?)?,
+
+        dll_characteristics: format!("0x{:x}", cursor.read_u16()?)
             .parse::<DllCharacteristics>()
             .map_err(|e| {
                 PeError::ParseError(format!(
@@ -52,30 +52,30 @@ pub fn parse_opt_header(cursor: &mut Cursor) -> Result<OptionalHeader, PeError>
                 ))
             })?,
         size_of_stack_reserve: match std_fields.magic {
-            PE32 => SizeOfStackReserve::PE32(cursor.read_u32()),
-            PE32_PLUS => SizeOfStackReserve::PE32_PLUS(cursor.read_u64()),
+            PE32 => SizeOfStackReserve::PE32(cursor.read_u32()?),
+            PE32_PLUS => SizeOfStackReserve::PE32_PLUS(cursor.read_u64()?),
         },
         size_of_stack_commit: match std_fields.magic {
-            PE32 => SizeOfStackCommit::PE32(cursor.read_u32()),
-            PE32_PLUS => SizeOfStackCommit::PE32_PLUS(cursor.read_u64()),
+            PE32 => SizeOfStackCommit::PE32(cursor.read_u32()?),
+            PE32_PLUS => SizeOfStackCommit::PE32_PLUS(cursor.read_u64()?),
         },
         size_of_heap_reserve: match std_fields.magic {
-            PE32 => SizeOfHeapReserve::PE32(cursor.read_u32()),
-            PE32_PLUS => SizeOfHeapReserve::PE32_PLUS(cursor.read_u64()),
+            PE32 => SizeOfHeapReserve::PE32(cursor.read_u32()?),
+            PE32_PLUS => SizeOfHeapReserve::PE32_PLUS(cursor.read_u64()?),
         },
         size_of_heap_commit: match std_fields.magic {
-            PE32 => SizeOfHeapCommit::PE32(cursor.read_u32()),
-            PE32_PLUS => SizeOfHeapCommit::PE32_PLUS(cursor.read_u64()),
+            PE32 => SizeOfHeapCommit::PE32(cursor.read_u32()?),
+            PE32_PLUS => SizeOfHeapCommit::PE32_PLUS(cursor.read_u64()?),
         },
-        loader_flags: cursor.read_u32(),
-        number_of_rva_and_sizes: cursor.read_u32(),
+        loader_flags: cursor.read_u32()?,
+        number_of_rva_and_sizes: cursor.read_u32()?,
     };
 
     macro_rules! image_data_dir {
         ($tag: expr) => {
             ImageDataDirectory {
-                virtual_address: cursor.read_u32(),
-                size: cursor.read_u32(),
+                virtual_address: cursor.read_u32()?,
+                size: cursor.read_u32()?,
                 tag: String::from($tag),
             }
         };
@@ -107,6 +107,27 @@ pub fn parse_opt_header(cursor: &mut Cursor) -> Result<OptionalHeader, PeError>
     })
 }
 
+/// Index of a data directory in the optional header
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#optional-header-data-directories-image-only
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ImageDirectoryEntry {
+    EXPORT,
+    IMPORT,
+    RESOURCE,
+    EXCEPTION,
+    SECURITY,
+    BASERELOC,
+    DEBUG,
+    ARCHITECTURE,
+    GLOBALPTR,
+    TLS,
+    LOAD_CONFIG,
+    BOUND_IMPORT,
+    IAT,
+    DELAY_IMPORT,
+    COM_DESCRIPTOR,
+}
+
 #[derive(Debug, Clone)]
 pub struct ImageDataDirectory {
     pub virtual_address: u32,
@@ -158,6 +179,26 @@ pub enum WindowsSubsystem {
     WINDOWS_BOOT_APPLICATION,
 }
 
+impl From<&WindowsSubsystem> for u16 {
+    fn from(subsystem: &WindowsSubsystem) -> u16 {
+        match subsystem {
+            WindowsSubsystem::UNKNOWN => 0,
+            WindowsSubsystem::NATIVE => 1,
+            WindowsSubsystem::WINDOWS_GUI => 2,
+            WindowsSubsystem::WINDOWS_CUI => 3,
+            WindowsSubsystem::POSIX_CUI => 7,
+            WindowsSubsystem::NATIVE_WINDOWS => 8,
+            WindowsSubsystem::WINDOWS_CE_GUI => 9,
+            WindowsSubsystem::EFI_APPLICATION => 10,
+            WindowsSubsystem::EFI_BOOT_SERVICE_DRIVER => 11,
+            WindowsSubsystem::EFI_RUNTIME_DRIVER => 12,
+            WindowsSubsystem::EFI_ROM => 13,
+            WindowsSubsystem::XBOX => 14,
+            WindowsSubsystem::WINDOWS_BOOT_APPLICATION => 16,
+        }
+    }
+}
+
 impl TryFrom<u16> for WindowsSubsystem {
     type Error = PeError;
     fn try_from(value: u16) -> Result<Self, PeError> {
@@ -191,30 +232,75 @@ pub enum ImageBase {
     PE32_PLUS(u64),
 }
 
+impl ImageBase {
+    pub fn value(&self) -> u64 {
+        match self {
+            ImageBase::PE32(base) => *base as u64,
+            ImageBase::PE32_PLUS(base) => *base,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum SizeOfStackReserve {
     PE32(u32),
     PE32_PLUS(u64),
 }
 
+impl SizeOfStackReserve {
+    pub fn value(&self) -> u64 {
+        match self {
+            SizeOfStackReserve::PE32(size) => *size as u64,
+            SizeOfStackReserve::PE32_PLUS(size) => *size,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum SizeOfStackCommit {
     PE32(u32),
     PE32_PLUS(u64),
 }
 
+impl SizeOfStackCommit {
+    pub fn value(&self) -> u64 {
+        match self {
+            SizeOfStackCommit::PE32(size) => *size as u64,
+            SizeOfStackCommit::PE32_PLUS(size) => *size,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum SizeOfHeapReserve {
     PE32(u32),
     PE32_PLUS(u64),
 }
 
+impl SizeOfHeapReserve {
+    pub fn value(&self) -> u64 {
+        match self {
+            SizeOfHeapReserve::PE32(size) => *size as u64,
+            SizeOfHeapReserve::PE32_PLUS(size) => *size,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum SizeOfHeapCommit {
     PE32(u32),
     PE32_PLUS(u64),
 }
 
+impl SizeOfHeapCommit {
+    pub fn value(&self) -> u64 {
+        match self {
+            SizeOfHeapCommit::PE32(size) => *size as u64,
+            SizeOfHeapCommit::PE32_PLUS(size) => *size,
+        }
+    }
+}
+
 /// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#optional-header-standard-fields-image-only
 #[derive(Debug, Clone)]
 pub struct StandardFields {
@@ -237,6 +323,59 @@ pub struct OptionalHeader {
     pub data_directories: Vec<ImageDataDirectory>,
 }
 
+impl OptionalHeader {
+    /// Serializes the header with all 16 data directories
+    pub fn to_bytes(&self) -> Vec<u8> {
+        use ExecutableKind::{PE32, PE32_PLUS};
+        let std = &self.std_fields;
+        let win = &self.win_specific_fields;
+        let mut out = Vec::with_capacity(240);
+        //  the fields that are pointer sized
+        let put_sized = |out: &mut Vec<u8>, value: u64| match std.magic {
+            PE32 => out.extend((value as u32).to_le_bytes()),
+            PE32_PLUS => out.extend(value.to_le_bytes()),
+        };
+        out.extend(u16::from(&std.magic).to_le_bytes());
+        out.push(std.major_linker_version);
+        out.push(std.minor_linker_version);
+        out.extend(std.size_of_code.to_le_bytes());
+        out.extend(std.size_of_initialized_data.to_le_bytes());
+        out.extend(std.size_of_uninitialized_data.to_le_bytes());
+        out.extend(std.address_of_entry_point.to_le_bytes());
+        out.extend(std.base_of_code.to_le_bytes());
+        if std.magic == PE32 {
+            out.extend(std.base_of_data.unwrap_or(0).to_le_bytes());
+        }
+
+        put_sized(&mut out, win.image_base.value());
+        out.extend(win.section_alignment.to_le_bytes());
+        out.extend(win.file_alignment.to_le_bytes());
+        out.extend(win.major_os_version.to_le_bytes());
+        out.extend(win.minor_os_version.to_le_bytes());
+        out.extend(win.major_image_version.to_le_bytes());
+        out.extend(win.minor_image_version.to_le_bytes());
+        out.extend(win.major_subsystem_version.to_le_bytes());
+        out.extend(win.minor_subsystem_version.to_le_bytes());
+        out.extend(win.win32_version_value.to_le_bytes());
+        out.extend(win.size_of_image.to_le_bytes());
+        out.extend(win.size_of_headers.to_le_bytes());
+        out.extend(win.checksum.to_le_bytes());
+        out.extend(u16::from(&win.subsystem).to_le_bytes());
+        out.extend(win.dll_characteristics.bits().to_le_bytes());
+        put_sized(&mut out, win.size_of_stack_reserve.value());
+        put_sized(&mut out, win.size_of_stack_commit.value());
+        put_sized(&mut out, win.size_of_heap_reserve.value());
+        put_sized(&mut out, win.size_of_heap_commit.value());
+        out.extend(win.loader_flags.to_le_bytes());
+        out.extend((self.data_directories.len() as u32).to_le_bytes());
+        for dir in &self.data_directories {
+            out.extend(dir.virtual_address.to_le_bytes());
+            out.extend(dir.size.to_le_bytes());
+        }
+        out
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PortableExecutable {
     pub executable_kind: ExecutableKind,
@@ -271,12 +410,22 @@ impl FromStr for DllCharacteristics {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
 pub enum ExecutableKind {
+    #[default]
     PE32,
     PE32_PLUS,
 }
 
+impl From<&ExecutableKind> for u16 {
+    fn from(kind: &ExecutableKind) -> u16 {
+        match kind {
+            ExecutableKind::PE32 => 0x10b,
+            ExecutableKind::PE32_PLUS => 0x20b,
+        }
+    }
+}
+
 impl TryFrom<u16> for ExecutableKind {
     type Error = PeError;
     fn try_from(val: u16) -> Result<Self, PeError> {
@@ -290,9 +439,3 @@ impl TryFrom<u16> for ExecutableKind {
         }
     }
 }
-
-impl Default for ExecutableKind {
-    fn default() -> Self {
-        Self::PE32
-    }
-}
@@ -0,0 +1,842 @@
+#![allow(non_camel_case_types)]
+
+use std::collections::HashSet;
+
+use crate::util::u16_from_bytes;
+
+use super::{
+    cursor::{slice_at, u16_at, u32_at, Cursor},
+    import_table::rva2foa,
+    optional_header::ImageDataDirectory,
+    section_table::SectionTable,
+    PeError,
+};
+
+//  the tree is usually type -> name -> language, anything deeper than this is garbage
+const MAX_DEPTH: usize = 8;
+
+/// Bounds the work a crafted tree can cause, depth alone doesn't limit the fan-out
+struct Walk {
+    /// Offsets of the directories and data entries already parsed, every node has one parent
+    visited: HashSet<usize>,
+    /// Bytes of resource data left to copy, the file size since leaves shouldn't overlap
+    budget: usize,
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#the-rsrc-section
+pub fn get_resource_table(
+    section_table: &SectionTable,
+    bytes: &[u8],
+    resource_table_dir: ImageDataDirectory,
+) -> Result<ResourceTable, PeError> {
+    if resource_table_dir.virtual_address == 0 || resource_table_dir.size == 0 {
+        return Err(PeError::MissingTable(
+            "The executable doesn't have a resource table".to_string(),
+        ));
+    }
+    let rsrc_foa = rva2foa(resource_table_dir.virtual_address, section_table)? as usize;
+    let rsrc = slice_at(bytes, rsrc_foa, resource_table_dir.size as usize)?;
+    let mut walk = Walk {
+        visited: HashSet::new(),
+        budget: bytes.len(),
+    };
+    let root = parse_resource_directory(section_table, bytes, rsrc, &mut walk, 0, 0)?;
+    Ok(ResourceTable { root })
+}
+
+fn parse_resource_directory(
+    section_table: &SectionTable,
+    bytes: &[u8],
+    rsrc: &[u8],
+    walk: &mut Walk,
+    offset: usize,
+    depth: usize,
+) -> Result<ResourceDirectory, PeError> {
+    if depth >= MAX_DEPTH {
+        return Err(PeError::ParseError(
+            "The resource tree is nested too deeply".to_string(),
+        ));
+    }
+    visit(walk, offset)?;
+    let mut cursor = Cursor::from_slice(rsrc, offset, 16)?;
+    let mut directory = ResourceDirectory {
+        characteristics: cursor.read_u32()?,
+        time_date_stamp: cursor.read_u32()?,
+        major_version: cursor.read_u16()?,
+        minor_version: cursor.read_u16()?,
+        number_of_name_entries: cursor.read_u16()?,
+        number_of_id_entries: cursor.read_u16()?,
+        entries: vec![],
+    };
+
+    let number_of_entries =
+        directory.number_of_name_entries as usize + directory.number_of_id_entries as usize;
+    for i in 0..number_of_entries {
+        let entry_offset = offset + 16 + i * 8;
+        let name_or_id = u32_at(rsrc, entry_offset)?;
+        let child_offset = u32_at(rsrc, entry_offset + 4)?;
+
+        let id = if name_or_id & 0x80000000 != 0 {
+            let name_offset = (name_or_id & 0x7FFFFFFF) as usize;
+            let length = u16_at(rsrc, name_offset)? as usize;
+            let name = slice_at(rsrc, name_offset + 2, length * 2)?
+                .chunks_exact(2)
+                .map(u16_from_bytes)
+                .collect::<Vec<u16>>();
+            ResourceId::Name(String::from_utf16_lossy(&name))
+        } else {
+            ResourceId::Id(name_or_id)
+        };
+
+        let child = if child_offset & 0x80000000 != 0 {
+            ResourceNode::Directory(parse_resource_directory(
+                section_table,
+                bytes,
+                rsrc,
+                walk,
+                (child_offset & 0x7FFFFFFF) as usize,
+                depth + 1,
+            )?)
+        } else {
+            visit(walk, child_offset as usize)?;
+            let mut cursor = Cursor::from_slice(rsrc, child_offset as usize, 16)?;
+            let mut data_entry = ResourceDataEntry {
+                data_rva: cursor.read_u32()?,
+                size: cursor.read_u32()?,
+                codepage: cursor.read_u32()?,
+                reserved: cursor.read_u32()?,
+                data: vec![],
+            };
+            walk.budget = walk
+                .budget
+                .checked_sub(data_entry.size as usize)
+                .ok_or_else(|| {
+                    PeError::ParseError("The resource data is larger than the file".to_string())
+                })?;
+            //  a leaf whose data isn't in the file keeps an empty payload, the others are still read
+            let data = section_table
+                .rva_to_offset(data_entry.data_rva)
+                .and_then(|foa| slice_at(bytes, foa as usize, data_entry.size as usize).ok());
+            if let Some(data) = data {
+                data_entry.data = data.to_vec();
+            }
+            ResourceNode::Data(data_entry)
+        };
+        directory.entries.push(ResourceDirectoryEntry { id, child });
+    }
+    Ok(directory)
+}
+
+fn visit(walk: &mut Walk, offset: usize) -> Result<(), PeError> {
+    if !walk.visited.insert(offset) {
+        return Err(PeError::ParseError(format!(
+            "The resource node at {:#x} is referenced twice",
+            offset
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct ResourceTable {
+    pub root: ResourceDirectory,
+}
+
+impl ResourceTable {
+    /// Flattens the type -> name -> language levels of the tree
+    pub fn resources(&self) -> Vec<Resource> {
+        let mut result = vec![];
+        for type_entry in &self.root.entries {
+            let ResourceNode::Directory(names) = &type_entry.child else {
+                continue;
+            };
+            for name_entry in &names.entries {
+                let ResourceNode::Directory(languages) = &name_entry.child else {
+                    continue;
+                };
+                for language_entry in &languages.entries {
+                    if let ResourceNode::Data(data) = &language_entry.child {
+                        result.push(Resource {
+                            r#type: type_entry.id.clone(),
+                            name: name_entry.id.clone(),
+                            language: language_entry.id.clone(),
+                            data: data.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    pub fn resources_of_type(&self, r#type: ResourceType) -> Vec<Resource> {
+        self.resources()
+            .into_iter()
+            .filter(|r| r.r#type.resource_type() == Some(r#type.clone()))
+            .collect()
+    }
+
+    /// The first RT_VERSION resource, decoded
+    pub fn version_info(&self) -> Result<VersionInfo, PeError> {
+        let resource = self
+            .resources_of_type(ResourceType::RT_VERSION)
+            .into_iter()
+            .next()
+            .ok_or(PeError::MissingTable(
+                "The executable doesn't have a version resource".to_string(),
+            ))?;
+        VersionInfo::parse(&resource.data.data)
+    }
+
+    /// The first RT_MANIFEST resource, as UTF-8 text
+    pub fn manifest(&self) -> Result<String, PeError> {
+        let resource = self
+            .resources_of_type(ResourceType::RT_MANIFEST)
+            .into_iter()
+            .next()
+            .ok_or(PeError::MissingTable(
+                "The executable doesn't have a manifest".to_string(),
+            ))?;
+        let data = &resource.data.data;
+        let data = data.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(data);
+        Ok(String::from_utf8_lossy(data).to_string())
+    }
+
+    /// Raw RT_ICON images, as (name, data)
+    pub fn icons(&self) -> Vec<(ResourceId, Vec<u8>)> {
+        self.resources_of_type(ResourceType::RT_ICON)
+            .into_iter()
+            .map(|r| (r.name, r.data.data))
+            .collect()
+    }
+
+    pub fn group_icons(&self) -> Result<Vec<GroupIcon>, PeError> {
+        self.resources_of_type(ResourceType::RT_GROUP_ICON)
+            .into_iter()
+            .map(|r| GroupIcon::parse(r.name, &r.data.data))
+            .collect()
+    }
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#resource-directory-table
+#[derive(Debug, Clone)]
+pub struct ResourceDirectory {
+    pub characteristics: u32,
+    pub time_date_stamp: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub number_of_name_entries: u16,
+    pub number_of_id_entries: u16,
+
+    ///  not in MS docs
+    pub entries: Vec<ResourceDirectoryEntry>,
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#resource-directory-entries
+#[derive(Debug, Clone)]
+pub struct ResourceDirectoryEntry {
+    pub id: ResourceId,
+    pub child: ResourceNode,
+}
+
+#[derive(Debug, Clone)]
+pub enum ResourceNode {
+    Directory(ResourceDirectory),
+    Data(ResourceDataEntry),
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#resource-data-entry
+#[derive(Debug, Clone)]
+pub struct ResourceDataEntry {
+    pub data_rva: u32,
+    pub size: u32,
+    pub codepage: u32,
+    pub reserved: u32,
+
+    ///  not in MS docs
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum ResourceId {
+    Id(u32),
+    Name(String),
+}
+
+impl ResourceId {
+    pub fn resource_type(&self) -> Option<ResourceType> {
+        match self {
+            ResourceId::Id(id) => ResourceType::try_from(*id).ok(),
+            ResourceId::Name(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ResourceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResourceId::Id(id) => write!(f, "#{}", id),
+            ResourceId::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Resource {
+    pub r#type: ResourceId,
+    pub name: ResourceId,
+    pub language: ResourceId,
+    pub data: ResourceDataEntry,
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/menurc/resource-types
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum ResourceType {
+    RT_CURSOR,
+    RT_BITMAP,
+    RT_ICON,
+    RT_MENU,
+    RT_DIALOG,
+    RT_STRING,
+    RT_FONTDIR,
+    RT_FONT,
+    RT_ACCELERATOR,
+    RT_RCDATA,
+    RT_MESSAGETABLE,
+    RT_GROUP_CURSOR,
+    RT_GROUP_ICON,
+    RT_VERSION,
+    RT_DLGINCLUDE,
+    RT_PLUGPLAY,
+    RT_VXD,
+    RT_ANICURSOR,
+    RT_ANIICON,
+    RT_HTML,
+    RT_MANIFEST,
+}
+
+impl TryFrom<u32> for ResourceType {
+    type Error = PeError;
+    fn try_from(value: u32) -> Result<Self, PeError> {
+        match value {
+            1 => Ok(Self::RT_CURSOR),
+            2 => Ok(Self::RT_BITMAP),
+            3 => Ok(Self::RT_ICON),
+            4 => Ok(Self::RT_MENU),
+            5 => Ok(Self::RT_DIALOG),
+            6 => Ok(Self::RT_STRING),
+            7 => Ok(Self::RT_FONTDIR),
+            8 => Ok(Self::RT_FONT),
+            9 => Ok(Self::RT_ACCELERATOR),
+            10 => Ok(Self::RT_RCDATA),
+            11 => Ok(Self::RT_MESSAGETABLE),
+            12 => Ok(Self::RT_GROUP_CURSOR),
+            14 => Ok(Self::RT_GROUP_ICON),
+            16 => Ok(Self::RT_VERSION),
+            17 => Ok(Self::RT_DLGINCLUDE),
+            19 => Ok(Self::RT_PLUGPLAY),
+            20 => Ok(Self::RT_VXD),
+            21 => Ok(Self::RT_ANICURSOR),
+            22 => Ok(Self::RT_ANIICON),
+            23 => Ok(Self::RT_HTML),
+            24 => Ok(Self::RT_MANIFEST),
+            _ => Err(PeError::ParseError(format!(
+                "Tried to parse an unknown resource type: {}",
+                value
+            ))),
+        }
+    }
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/menurc/vs-versioninfo
+#[derive(Debug, Clone)]
+pub struct VersionInfo {
+    pub fixed_file_info: Option<FixedFileInfo>,
+    pub string_file_info: Vec<StringTable>,
+    /// (language, codepage) pairs from VarFileInfo\Translation
+    pub translations: Vec<(u16, u16)>,
+}
+
+impl VersionInfo {
+    pub fn parse(data: &[u8]) -> Result<VersionInfo, PeError> {
+        let root = VersionBlock::parse(data, 0, 0)?;
+        if root.key != "VS_VERSION_INFO" {
+            return Err(PeError::ParseError(format!(
+                "Expected a VS_VERSION_INFO block, found {}",
+                root.key
+            )));
+        }
+        let fixed_file_info = match root.value.len() >= 52 {
+            true => Some(FixedFileInfo::parse(root.value)?),
+            false => None,
+        };
+
+        let mut string_file_info = vec![];
+        let mut translations = vec![];
+        for child in &root.children {
+            match child.key.as_str() {
+                "StringFileInfo" => {
+                    for table in &child.children {
+                        string_file_info.push(StringTable {
+                            key: table.key.clone(),
+                            strings: table
+                                .children
+                                .iter()
+                                .map(|s| (s.key.clone(), utf16_until_null(s.value)))
+                                .collect(),
+                        });
+                    }
+                }
+                "VarFileInfo" => {
+                    for var in child.children.iter().filter(|v| v.key == "Translation") {
+                        translations.extend(var.value.chunks_exact(4).map(|pair| {
+                            (u16_from_bytes(&pair[0..2]), u16_from_bytes(&pair[2..4]))
+                        }));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(VersionInfo {
+            fixed_file_info,
+            string_file_info,
+            translations,
+        })
+    }
+
+    pub fn file_version(&self) -> Option<(u16, u16, u16, u16)> {
+        self.fixed_file_info.as_ref().map(|f| f.file_version())
+    }
+
+    pub fn product_version(&self) -> Option<(u16, u16, u16, u16)> {
+        self.fixed_file_info.as_ref().map(|f| f.product_version())
+    }
+
+    /// Looks `key` up in the first string table that has it
+    pub fn get_string(&self, key: &str) -> Option<&str> {
+        self.string_file_info.iter().find_map(|table| {
+            table
+                .strings
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        })
+    }
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/menurc/stringtable
+#[derive(Debug, Clone)]
+pub struct StringTable {
+    /// Language and codepage as 8 hex digits, e.g. `040904b0`
+    pub key: String,
+    pub strings: Vec<(String, String)>,
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/api/verrsrc/ns-verrsrc-vs_fixedfileinfo
+#[derive(Debug, Clone)]
+pub struct FixedFileInfo {
+    pub signature: u32,
+    pub struc_version: u32,
+    pub file_version_ms: u32,
+    pub file_version_ls: u32,
+    pub product_version_ms: u32,
+    pub product_version_ls: u32,
+    pub file_flags_mask: u32,
+    pub file_flags: u32,
+    pub file_os: u32,
+    pub file_type: u32,
+    pub file_subtype: u32,
+    pub file_date_ms: u32,
+    pub file_date_ls: u32,
+}
+
+impl FixedFileInfo {
+    const SIGNATURE: u32 = 0xFEEF04BD;
+
+    fn parse(data: &[u8]) -> Result<FixedFileInfo, PeError> {
+        let mut cursor = Cursor::from_slice(data, 0, 52)?;
+        let result = FixedFileInfo {
+            signature: cursor.read_u32()?,
+            struc_version: cursor.read_u32()?,
+            file_version_ms: cursor.read_u32()?,
+            file_version_ls: cursor.read_u32()?,
+            product_version_ms: cursor.read_u32()?,
+            product_version_ls: cursor.read_u32()?,
+            file_flags_mask: cursor.read_u32()?,
+            file_flags: cursor.read_u32()?,
+            file_os: cursor.read_u32()?,
+            file_type: cursor.read_u32()?,
+            file_subtype: cursor.read_u32()?,
+            file_date_ms: cursor.read_u32()?,
+            file_date_ls: cursor.read_u32()?,
+        };
+        if result.signature != Self::SIGNATURE {
+            return Err(PeError::ParseError(format!(
+                "Invalid VS_FIXEDFILEINFO signature: {:#x}",
+                result.signature
+            )));
+        }
+        Ok(result)
+    }
+
+    pub fn file_version(&self) -> (u16, u16, u16, u16) {
+        split_version(self.file_version_ms, self.file_version_ls)
+    }
+
+    pub fn product_version(&self) -> (u16, u16, u16, u16) {
+        split_version(self.product_version_ms, self.product_version_ls)
+    }
+}
+
+fn split_version(ms: u32, ls: u32) -> (u16, u16, u16, u16) {
+    ((ms >> 16) as u16, ms as u16, (ls >> 16) as u16, ls as u16)
+}
+
+fn utf16_until_null(data: &[u8]) -> String {
+    let chars = data
+        .chunks_exact(2)
+        .map(u16_from_bytes)
+        .take_while(|c| *c != 0)
+        .collect::<Vec<u16>>();
+    String::from_utf16_lossy(&chars)
+}
+
+fn align_4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+//  VS_VERSION_INFO -> StringFileInfo -> StringTable -> String is as deep as real trees go
+const MAX_VERSION_DEPTH: usize = 8;
+
+/// Every node in a VS_VERSIONINFO tree shares this layout:
+/// wLength, wValueLength, wType, szKey, padding, Value, padding, Children
+struct VersionBlock<'a> {
+    key: String,
+    value: &'a [u8],
+    children: Vec<VersionBlock<'a>>,
+}
+
+impl<'a> VersionBlock<'a> {
+    fn parse(data: &'a [u8], offset: usize, depth: usize) -> Result<VersionBlock<'a>, PeError> {
+        if depth >= MAX_VERSION_DEPTH {
+            return Err(PeError::ParseError(
+                "The version resource is nested too deeply".to_string(),
+            ));
+        }
+        let truncated = || {
+            PeError::ParseError(format!(
+                "Truncated version resource block at offset {:#x}",
+                offset
+            ))
+        };
+        let header = data.get(offset..offset + 6).ok_or_else(truncated)?;
+        let length = u16_from_bytes(&header[0..2]) as usize;
+        let value_length = u16_from_bytes(&header[2..4]) as usize;
+        let is_text = u16_from_bytes(&header[4..6]) == 1;
+        let end = offset + length;
+        if length < 6 || end > data.len() {
+            return Err(truncated());
+        }
+
+        let key_start = offset + 6;
+        let key = utf16_until_null(&data[key_start..end]);
+        let value_start = align_4(key_start + (key.encode_utf16().count() + 1) * 2);
+        //  text values are measured in words
+        let value_size = match is_text {
+            true => value_length * 2,
+            false => value_length,
+        };
+        let value_end = (value_start + value_size).min(end);
+        let value = data.get(value_start..value_end).unwrap_or(&[]);
+
+        let mut children = vec![];
+        let mut child_offset = align_4(value_end);
+        while child_offset + 6 <= end {
+            let child = VersionBlock::parse(&data[..end], child_offset, depth + 1)?;
+            let child_length = u16_from_bytes(&data[child_offset..]) as usize;
+            children.push(child);
+            child_offset = align_4(child_offset + child_length);
+        }
+        Ok(VersionBlock {
+            key,
+            value,
+            children,
+        })
+    }
+}
+
+/// https://learn.microsoft.com/en-us/previous-versions/ms997538(v=msdn.10)
+#[derive(Debug, Clone)]
+pub struct GroupIcon {
+    pub name: ResourceId,
+    pub entries: Vec<GroupIconEntry>,
+    pub raw_data: Vec<u8>,
+}
+
+/// GRPICONDIRENTRY, `id` is the name of the matching RT_ICON resource
+#[derive(Debug, Clone)]
+pub struct GroupIconEntry {
+    pub width: u8,
+    pub height: u8,
+    pub color_count: u8,
+    pub reserved: u8,
+    pub planes: u16,
+    pub bit_count: u16,
+    pub bytes_in_res: u32,
+    pub id: u16,
+}
+
+impl GroupIcon {
+    fn parse(name: ResourceId, data: &[u8]) -> Result<GroupIcon, PeError> {
+        if data.len() < 6 {
+            return Err(PeError::ParseError(
+                "Truncated group icon resource".to_string(),
+            ));
+        }
+        let count = u16_from_bytes(&data[4..6]) as usize;
+        if data.len() < 6 + count * 14 {
+            return Err(PeError::ParseError(
+                "Truncated group icon resource".to_string(),
+            ));
+        }
+        let mut cursor = Cursor::new(&data[6..6 + count * 14]);
+        let entries = (0..count)
+            .map(|_| {
+                Ok(GroupIconEntry {
+                    width: cursor.read_u8()?,
+                    height: cursor.read_u8()?,
+                    color_count: cursor.read_u8()?,
+                    reserved: cursor.read_u8()?,
+                    planes: cursor.read_u16()?,
+                    bit_count: cursor.read_u16()?,
+                    bytes_in_res: cursor.read_u32()?,
+                    id: cursor.read_u16()?,
+                })
+            })
+            .collect::<Result<Vec<GroupIconEntry>, PeError>>()?;
+        Ok(GroupIcon {
+            name,
+            entries,
+            raw_data: data.to_vec(),
+        })
+    }
+
+    /// Builds a standalone .ico file out of this group and the RT_ICON resources it refers to
+    pub fn to_ico(&self, icons: &[(ResourceId, Vec<u8>)]) -> Vec<u8> {
+        let images = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                icons
+                    .iter()
+                    .find(|(name, _)| *name == ResourceId::Id(entry.id as u32))
+                    .map(|(_, data)| (entry, data))
+            })
+            .collect::<Vec<_>>();
+
+        let mut ico = vec![];
+        ico.extend_from_slice(&0u16.to_le_bytes());
+        ico.extend_from_slice(&1u16.to_le_bytes());
+        ico.extend_from_slice(&(images.len() as u16).to_le_bytes());
+        let mut image_offset = 6 + images.len() as u32 * 16;
+        for (entry, data) in &images {
+            ico.extend_from_slice(&[entry.width, entry.height, entry.color_count, 0]);
+            ico.extend_from_slice(&entry.planes.to_le_bytes());
+            ico.extend_from_slice(&entry.bit_count.to_le_bytes());
+            ico.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            ico.extend_from_slice(&image_offset.to_le_bytes());
+            image_offset += data.len() as u32;
+        }
+        for (_, data) in &images {
+            ico.extend_from_slice(data);
+        }
+        ico
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pe::{
+        optional_header::ImageDataDirectory,
+        test_util::{put_u16, put_u32, section, section_table},
+        PeError, PortableExecutable,
+    };
+
+    use super::{get_resource_table, ResourceId, ResourceNode, ResourceType, VersionInfo};
+
+    #[test]
+    fn walks_the_tree() {
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        let table = pe.resource_table().unwrap();
+        let resources = table.resources();
+        assert_eq!(resources.len(), 1);
+        assert_eq!(
+            resources[0].r#type.resource_type(),
+            Some(ResourceType::RT_MANIFEST)
+        );
+        assert_eq!(resources[0].name, ResourceId::Id(1));
+        assert_eq!(resources[0].language, ResourceId::Id(0x409));
+        assert_eq!(resources[0].data.size, 0x17d);
+
+        let manifest = table.manifest().unwrap();
+        assert!(manifest.starts_with("<?xml version='1.0'"));
+        assert!(manifest.contains("asInvoker"));
+        assert!(matches!(
+            table.version_info(),
+            Err(PeError::MissingTable(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_self_referencing_directories() {
+        let section_table = section_table([section(".rsrc", 0x1000, 0, 0x40)]);
+        let dir = || ImageDataDirectory {
+            virtual_address: 0x1000,
+            size: 0x40,
+            tag: "resource_table".to_string(),
+        };
+        //  a root whose two entries point back at itself would be walked 2^8 times
+        let mut bytes = vec![0u8; 0x40];
+        put_u16(&mut bytes, 14, 2);
+        put_u32(&mut bytes, 16, 1);
+        put_u32(&mut bytes, 20, 0x80000000);
+        put_u32(&mut bytes, 24, 2);
+        put_u32(&mut bytes, 28, 0x80000000);
+        assert!(matches!(
+            get_resource_table(&section_table, &bytes, dir()),
+            Err(PeError::ParseError(_))
+        ));
+
+        //  two leaves sharing one data entry
+        put_u32(&mut bytes, 20, 0x20);
+        put_u32(&mut bytes, 28, 0x20);
+        put_u32(&mut bytes, 0x20, 0x1000);
+        put_u32(&mut bytes, 0x24, 0x10);
+        assert!(matches!(
+            get_resource_table(&section_table, &bytes, dir()),
+            Err(PeError::ParseError(_))
+        ));
+
+        put_u16(&mut bytes, 14, 1);
+        let table = get_resource_table(&section_table, &bytes, dir()).unwrap();
+        assert_eq!(table.root.entries.len(), 1);
+    }
+
+    #[test]
+    fn keeps_leaves_without_file_data() {
+        let section_table = section_table([section(".rsrc", 0x1000, 0, 0x40)]);
+        let dir = ImageDataDirectory {
+            virtual_address: 0x1000,
+            size: 0x40,
+            tag: "resource_table".to_string(),
+        };
+        //  a leaf whose data is past the end of the section
+        let mut bytes = vec![0u8; 0x40];
+        put_u16(&mut bytes, 14, 1);
+        put_u32(&mut bytes, 16, 1);
+        put_u32(&mut bytes, 20, 0x20);
+        put_u32(&mut bytes, 0x20, 0x5000);
+        put_u32(&mut bytes, 0x24, 0x10);
+        let table = get_resource_table(&section_table, &bytes, dir).unwrap();
+        assert!(matches!(
+            &table.root.entries[0].child,
+            ResourceNode::Data(data) if data.size == 0x10 && data.data.is_empty()
+        ));
+    }
+
+    fn utf16(value: &str) -> Vec<u8> {
+        value
+            .encode_utf16()
+            .chain([0])
+            .flat_map(|c| c.to_le_bytes())
+            .collect()
+    }
+
+    fn block(key: &str, value: &[u8], is_text: bool, children: &[Vec<u8>]) -> Vec<u8> {
+        let mut result = vec![0u8; 6];
+        result.extend(utf16(key));
+        while !result.len().is_multiple_of(4) {
+            result.push(0);
+        }
+        result.extend_from_slice(value);
+        for child in children {
+            while !result.len().is_multiple_of(4) {
+                result.push(0);
+            }
+            result.extend(child);
+        }
+        let value_length = match is_text {
+            true => value.len() / 2,
+            false => value.len(),
+        };
+        let length = result.len() as u16;
+        result[0..2].copy_from_slice(&length.to_le_bytes());
+        result[2..4].copy_from_slice(&(value_length as u16).to_le_bytes());
+        result[4..6].copy_from_slice(&(is_text as u16).to_le_bytes());
+        result
+    }
+
+    #[test]
+    fn parses_version_info() {
+        let mut fixed = vec![];
+        for value in [
+            0xFEEF04BDu32,
+            0x10000,
+            0x00010002,
+            0x00030004,
+            0x00050006,
+            0x00070008,
+            0x3f,
+            0,
+            0x40004,
+            1,
+            0,
+            0,
+            0,
+        ] {
+            fixed.extend_from_slice(&value.to_le_bytes());
+        }
+        let strings = block(
+            "StringFileInfo",
+            &[],
+            true,
+            &[block(
+                "040904b0",
+                &[],
+                true,
+                &[
+                    block("ProductName", &utf16("Resident Evil 5"), true, &[]),
+                    block("FileVersion", &utf16("1.2.3.4"), true, &[]),
+                ],
+            )],
+        );
+        let vars = block(
+            "VarFileInfo",
+            &[],
+            true,
+            &[block("Translation", &[0x09, 0x04, 0xb0, 0x04], false, &[])],
+        );
+        let data = block("VS_VERSION_INFO", &fixed, false, &[strings, vars]);
+
+        let info = VersionInfo::parse(&data).unwrap();
+        assert_eq!(info.file_version(), Some((1, 2, 3, 4)));
+        assert_eq!(info.product_version(), Some((5, 6, 7, 8)));
+        assert_eq!(info.string_file_info[0].key, "040904b0");
+        assert_eq!(info.get_string("ProductName"), Some("Resident Evil 5"));
+        assert_eq!(info.get_string("FileVersion"), Some("1.2.3.4"));
+        assert_eq!(info.translations, vec![(0x409, 0x4b0)]);
+    }
+
+    #[test]
+    fn rejects_deeply_nested_version_info() {
+        let mut nested = block("Leaf", &[], true, &[]);
+        for _ in 0..10 {
+            nested = block("Block", &[], true, &[nested]);
+        }
+        let data = block("VS_VERSION_INFO", &[], false, &[nested]);
+        assert!(matches!(
+            VersionInfo::parse(&data),
+            Err(PeError::ParseError(_))
+        ));
+    }
+}
@@ -0,0 +1,259 @@
+use crate::util::u32_from_bytes;
+
+use super::PeError;
+
+//  "Rich" and "DanS"
+const RICH_SIGNATURE: u32 = 0x68636952;
+const DANS_SIGNATURE: u32 = 0x536e6144;
+//  the DOS stub and the header can't start before the end of IMAGE_DOS_HEADER
+const DOS_HEADER_SIZE: usize = 0x40;
+const E_LFANEW_OFFSET: usize = 0x3c;
+
+/// The undocumented header the MSVC linker puts between the DOS stub and the PE signature
+pub fn get_rich_header(bytes: &[u8], e_lfanew: u32) -> Result<RichHeader, PeError> {
+    let missing = || PeError::MissingTable("The executable doesn't have a Rich header".to_string());
+    let end = (e_lfanew as usize).min(bytes.len());
+    let rich_offset = (DOS_HEADER_SIZE..end.saturating_sub(8))
+        .step_by(4)
+        .find(|offset| u32_from_bytes(&bytes[*offset..]) == RICH_SIGNATURE)
+        .ok_or_else(missing)?;
+    let key = u32_from_bytes(&bytes[rich_offset + 4..]);
+
+    let dans_offset = (DOS_HEADER_SIZE..rich_offset)
+        .step_by(4)
+        .rev()
+        .find(|offset| u32_from_bytes(&bytes[*offset..]) ^ key == DANS_SIGNATURE)
+        .ok_or_else(missing)?;
+
+    //  DanS is followed by 3 padding dwords that decode to zero
+    let entries = bytes
+        .get(dans_offset + 16..rich_offset)
+        .ok_or_else(missing)?
+        .chunks_exact(8)
+        .map(|entry| {
+            let comp_id = u32_from_bytes(entry) ^ key;
+            RichEntry {
+                product_id: (comp_id >> 16) as u16,
+                build: comp_id as u16,
+                count: u32_from_bytes(&entry[4..]) ^ key,
+            }
+        })
+        .collect::<Vec<RichEntry>>();
+
+    let checksum = compute_rich_checksum(bytes, dans_offset, &entries);
+    Ok(RichHeader {
+        offset: dans_offset,
+        size: rich_offset + 8 - dans_offset,
+        key,
+        checksum_valid: checksum == key,
+        entries,
+    })
+}
+
+/// The key is a checksum of the DOS header, the DOS stub and the decoded entries
+fn compute_rich_checksum(bytes: &[u8], dans_offset: usize, entries: &[RichEntry]) -> u32 {
+    let mut checksum = dans_offset as u32;
+    for (idx, byte) in bytes[..dans_offset].iter().enumerate() {
+        //  e_lfanew isn't known when the linker computes the checksum
+        if (E_LFANEW_OFFSET..E_LFANEW_OFFSET + 4).contains(&idx) {
+            continue;
+        }
+        checksum = checksum.wrapping_add((*byte as u32).rotate_left(idx as u32));
+    }
+    for entry in entries {
+        checksum = checksum.wrapping_add(entry.comp_id().rotate_left(entry.count));
+    }
+    checksum
+}
+
+#[derive(Debug, Clone)]
+pub struct RichHeader {
+    /// File offset of the "DanS" marker
+    pub offset: usize,
+    /// Size in bytes up to and including the "Rich" marker and the key
+    pub size: usize,
+    /// XOR key, also the checksum of everything before the header
+    pub key: u32,
+    pub checksum_valid: bool,
+    pub entries: Vec<RichEntry>,
+}
+
+impl RichHeader {
+    /// The decoded bytes from "DanS" up to the "Rich" marker
+    pub fn decoded(&self, bytes: &[u8]) -> Vec<u8> {
+        bytes[self.offset..self.offset + self.size - 8]
+            .chunks_exact(4)
+            .flat_map(|dword| (u32_from_bytes(dword) ^ self.key).to_le_bytes())
+            .collect()
+    }
+
+    /// The newest toolchain that contributed an object to the image
+    pub fn visual_studio(&self) -> Option<VisualStudio> {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.visual_studio())
+            .max()
+    }
+}
+
+/// One (product id, build number, count) tuple, aka @comp.id
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct RichEntry {
+    pub product_id: u16,
+    pub build: u16,
+    /// How many objects this tool produced
+    pub count: u32,
+}
+
+impl RichEntry {
+    pub fn comp_id(&self) -> u32 {
+        (self.product_id as u32) << 16 | self.build as u32
+    }
+
+    pub fn product(&self) -> Option<&'static str> {
+        product_name(self.product_id)
+    }
+
+    pub fn visual_studio(&self) -> Option<VisualStudio> {
+        VisualStudio::from_comp_id(self.product_id, self.build)
+    }
+}
+
+/// Names of the product ids emitted by the VS2015+ toolchains, which every later release reuses
+pub fn product_name(product_id: u16) -> Option<&'static str> {
+    let name = match product_id {
+        0x0000 => "Unknown",
+        0x0001 => "Import0",
+        0x00fd => "AliasObj1400",
+        0x00fe => "Cvtpgd1400",
+        0x00ff => "Cvtres1400",
+        0x0100 => "Export1400",
+        0x0101 => "Implib1400",
+        0x0102 => "Linker1400",
+        0x0103 => "Masm1400",
+        0x0104 => "Utc1900_C",
+        0x0105 => "Utc1900_CPP",
+        0x0106 => "Utc1900_CVTCIL_C",
+        0x0107 => "Utc1900_CVTCIL_CPP",
+        0x0108 => "Utc1900_LTCG_C",
+        0x0109 => "Utc1900_LTCG_CPP",
+        0x010a => "Utc1900_LTCG_MSIL",
+        0x010b => "Utc1900_POGO_I_C",
+        0x010c => "Utc1900_POGO_I_CPP",
+        0x010d => "Utc1900_POGO_O_C",
+        0x010e => "Utc1900_POGO_O_CPP",
+        _ => return None,
+    };
+    Some(name)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum VisualStudio {
+    VS6,
+    VS2002,
+    VS2003,
+    VS2005,
+    VS2008,
+    VS2010,
+    VS2012,
+    VS2013,
+    VS2015,
+    VS2017,
+    VS2019,
+    VS2022,
+}
+
+impl VisualStudio {
+    /// Product ids are allocated in blocks per release, VS2015 and later share theirs
+    /// and can only be told apart by the build number
+    pub fn from_comp_id(product_id: u16, build: u16) -> Option<Self> {
+        let version = match product_id {
+            0x0002..=0x0018 => VisualStudio::VS6,
+            0x0019..=0x0059 => VisualStudio::VS2002,
+            0x005a..=0x006c => VisualStudio::VS2003,
+            0x006d..=0x0082 => VisualStudio::VS2005,
+            0x0083..=0x0097 => VisualStudio::VS2008,
+            0x0098..=0x00c6 => VisualStudio::VS2010,
+            0x00c7..=0x00da => VisualStudio::VS2012,
+            0x00db..=0x00fc => VisualStudio::VS2013,
+            0x00fd..=0x010e => match build {
+                0..=24999 => VisualStudio::VS2015,
+                25000..=27499 => VisualStudio::VS2017,
+                27500..=30699 => VisualStudio::VS2019,
+                _ => VisualStudio::VS2022,
+            },
+            _ => return None,
+        };
+        Some(version)
+    }
+
+    /// The version reported by cl.exe
+    pub fn msvc_version(&self) -> &'static str {
+        match self {
+            VisualStudio::VS6 => "6.0",
+            VisualStudio::VS2002 => "7.0",
+            VisualStudio::VS2003 => "7.1",
+            VisualStudio::VS2005 => "8.0",
+            VisualStudio::VS2008 => "9.0",
+            VisualStudio::VS2010 => "10.0",
+            VisualStudio::VS2012 => "11.0",
+            VisualStudio::VS2013 => "12.0",
+            VisualStudio::VS2015 => "14.0",
+            VisualStudio::VS2017 => "14.1x",
+            VisualStudio::VS2019 => "14.2x",
+            VisualStudio::VS2022 => "14.3x",
+        }
+    }
+}
+
+impl std::fmt::Display for VisualStudio {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            VisualStudio::VS6 => "Visual Studio 6.0",
+            VisualStudio::VS2002 => "Visual Studio .NET 2002",
+            VisualStudio::VS2003 => "Visual Studio .NET 2003",
+            VisualStudio::VS2005 => "Visual Studio 2005",
+            VisualStudio::VS2008 => "Visual Studio 2008",
+            VisualStudio::VS2010 => "Visual Studio 2010",
+            VisualStudio::VS2012 => "Visual Studio 2012",
+            VisualStudio::VS2013 => "Visual Studio 2013",
+            VisualStudio::VS2015 => "Visual Studio 2015",
+            VisualStudio::VS2017 => "Visual Studio 2017",
+            VisualStudio::VS2019 => "Visual Studio 2019",
+            VisualStudio::VS2022 => "Visual Studio 2022",
+        };
+        write!(f, "{} (MSVC {})", name, self.msvc_version())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pe::{PeError, PortableExecutable};
+
+    use super::VisualStudio;
+
+    #[test]
+    fn decodes_rich_header() {
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        assert_eq!(pe.dos_header.e_lfanew, 0xe0);
+        let rich = pe.rich_header().unwrap();
+        assert_eq!(rich.offset, 0x80);
+        assert_eq!(rich.key, 0x7e5fdc06);
+        assert!(rich.checksum_valid);
+        assert_eq!(rich.entries.len(), 8);
+
+        let linker = rich.entries.last().unwrap();
+        assert_eq!(linker.product(), Some("Linker1400"));
+        assert_eq!(linker.build, 32532);
+        assert_eq!(linker.count, 1);
+        let cpp = &rich.entries[1];
+        assert_eq!(cpp.product(), Some("Utc1900_CPP"));
+        assert_eq!(cpp.count, 25);
+        assert_eq!(rich.visual_studio(), Some(VisualStudio::VS2022));
+        assert_eq!(rich.decoded(&pe.bytes)[..4], *b"DanS");
+
+        //  MinGW doesn't emit one
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        assert!(matches!(pe.rich_header(), Err(PeError::MissingTable(_))));
+    }
+}
@@ -1,6 +1,6 @@
 #![allow(non_camel_case_types)]
 
-use super::{cursor::Cursor, PeError};
+use super::{cursor::Cursor, file_header::Machine, PeError};
 use std::str::FromStr;
 
 #[derive(Debug, Clone)]
@@ -10,68 +10,82 @@ pub struct SectionTable {
 
 impl SectionTable {
     pub fn get_section_header(&self, name: &str) -> Option<&SectionHeader> {
-        //  FIXME: correctly compare the names
         self.section_headers
             .iter()
-            .find(|section| section.name.contains(name))
+            .find(|section| section.name() == name)
     }
 
-    /// FIXME: i'm not done
-    pub fn get_export_table_x64(&self) -> Result<Vec<ExportTableEntryX64>, PeError> {
-        let header = self
-            .get_section_header(".pdata")
-            .ok_or(PeError::MissingSection(
-                "Could not find the .pdata section".to_string(),
-            ))?;
-        let mut cursor = Cursor::new(header.raw_data.clone());
-        let mut entries = vec![];
-        loop {
-            let entry = ExportTableEntryX64 {
-                begin_address: cursor.read_u32(),
-                end_address: cursor.read_u32(),
-                unwind_info_address: cursor.read_u32(),
-            };
-            if entry.begin_address == 0 && entry.end_address == 0 && entry.unwind_info_address == 0
-            {
-                break;
-            }
-            entries.push(entry);
+    /// The section whose memory range contains `rva`
+    pub fn section_for_rva(&self, rva: u32) -> Option<&SectionHeader> {
+        self.section_headers.iter().find(|section| {
+            rva >= section.virtual_address
+                && rva - section.virtual_address < section.virtual_extent()
+        })
+    }
+
+    /// File offset of `rva`. RVAs before the first section are in the headers, which are mapped
+    /// as is. `None` if the RVA isn't in a section or in its virtual only tail, e.g. in .bss
+    pub fn rva_to_offset(&self, rva: u32) -> Option<u32> {
+        if rva < self.first_section_rva() {
+            return Some(rva);
+        }
+        let section = self.section_for_rva(rva)?;
+        let delta = rva - section.virtual_address;
+        if delta >= section.size_of_raw_data {
+            return None;
         }
+        section.ptr_to_raw_data.checked_add(delta)
+    }
 
-        Ok(vec![])
+    /// The RVA the byte at `offset` is mapped to, `None` if the loader doesn't map it
+    pub fn offset_to_rva(&self, offset: u32) -> Option<u32> {
+        let first_section_offset = self
+            .section_headers
+            .iter()
+            .filter(|section| section.size_of_raw_data != 0)
+            .map(|section| section.ptr_to_raw_data)
+            .min()
+            .unwrap_or(u32::MAX);
+        if offset < first_section_offset.min(self.first_section_rva()) {
+            return Some(offset);
+        }
+        self.section_headers.iter().find_map(|section| {
+            let delta = offset.checked_sub(section.ptr_to_raw_data)?;
+            //  raw data past the virtual size is padding and isn't mapped
+            (delta < section.size_of_raw_data.min(section.virtual_extent()))
+                .then(|| section.virtual_address.checked_add(delta))
+                .flatten()
+        })
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct ExportTableEntryX64 {
-    pub begin_address: u32,
-    pub end_address: u32,
-    pub unwind_info_address: u32,
+    fn first_section_rva(&self) -> u32 {
+        self.section_headers
+            .iter()
+            .map(|section| section.virtual_address)
+            .min()
+            .unwrap_or(0)
+    }
 }
 
 /// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#section-table-section-headers
 pub fn parse_section_header(cursor: &mut Cursor) -> Result<SectionHeader, PeError> {
-    let mut result = SectionHeader {
-        name: cursor.read_str(8).to_string(),
-        virtual_size: cursor.read_u32(),
-        virtual_address: cursor.read_u32(),
-        size_of_raw_data: cursor.read_u32(),
-        ptr_to_raw_data: cursor.read_u32(),
-        ptr_to_relocations: cursor.read_u32(),
-        ptr_to_linenumbers: cursor.read_u32(),
-        number_of_relocations: cursor.read_u16(),
-        number_of_linenumbers: cursor.read_u16(),
-        characteristics: format!("0x{:x}", cursor.read_u32())
+    Ok(SectionHeader {
+        name: cursor.read_str(8)?.to_string(),
+        virtual_size: cursor.read_u32()?,
+        virtual_address: cursor.read_u32()?,
+        size_of_raw_data: cursor.read_u32()?,
+        ptr_to_raw_data: cursor.read_u32()?,
+        ptr_to_relocations: cursor.read_u32()?,
+        ptr_to_linenumbers: cursor.read_u32()?,
+        number_of_relocations: cursor.read_u16()?,
+        number_of_linenumbers: cursor.read_u16()?,
+        characteristics: format!("0x{:x}", cursor.read_u32()?)
             .parse::<SectionFlags>()
             .map_err(|e| {
                 PeError::ParseError(format!("Could not parse the section flags: {}", e))
             })?,
         raw_data: vec![],
-    };
-    result.raw_data = cursor.bytes[result.ptr_to_raw_data as usize
-        ..result.ptr_to_raw_data as usize + result.size_of_raw_data as usize]
-        .to_vec();
-    Ok(result)
+    })
 }
 
 pub fn parse_section_headers(
@@ -98,7 +112,8 @@ pub struct SectionHeader {
     pub number_of_linenumbers: u16,
     pub characteristics: SectionFlags,
 
-    ///  not in MS docs
+    ///  not in MS docs, a copy of the section's file data
+    ///  filled by PortableExecutable, PeView leaves it empty and borrows it instead
     pub raw_data: Vec<u8>,
 }
 
@@ -119,75 +134,74 @@ impl std::fmt::Display for SectionHeader {
 }
 
 impl SectionHeader {
-    //  TODO: test this
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(40);
+        let mut name = [0u8; 8];
+        let len = self.name.len().min(8);
+        name[..len].copy_from_slice(&self.name.as_bytes()[..len]);
+        out.extend(name);
+        out.extend(self.virtual_size.to_le_bytes());
+        out.extend(self.virtual_address.to_le_bytes());
+        out.extend(self.size_of_raw_data.to_le_bytes());
+        out.extend(self.ptr_to_raw_data.to_le_bytes());
+        out.extend(self.ptr_to_relocations.to_le_bytes());
+        out.extend(self.ptr_to_linenumbers.to_le_bytes());
+        out.extend(self.number_of_relocations.to_le_bytes());
+        out.extend(self.number_of_linenumbers.to_le_bytes());
+        out.extend(self.characteristics.bits().to_le_bytes());
+        out
+    }
+
+    /// The name without the null padding
+    pub fn name(&self) -> &str {
+        self.name.trim_end_matches('\0')
+    }
+
+    /// Size in memory, linkers that leave VirtualSize empty mean SizeOfRawData
+    pub(super) fn virtual_extent(&self) -> u32 {
+        match self.virtual_size {
+            0 => self.size_of_raw_data,
+            virtual_size => virtual_size,
+        }
+    }
+
     /// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#coff-relocations-object-only
-    pub fn coff_relocations<'a>(
+    pub fn coff_relocations(
         &self,
-        memory: &'a Vec<u8>,
+        memory: &[u8],
+        machine: &Machine,
     ) -> Result<Vec<CoffRelocation>, PeError> {
-        let mut cursor = Cursor::new(
-            memory[self.ptr_to_relocations as usize
-                ..self.ptr_to_relocations as usize + (self.number_of_relocations as usize * 10)]
-                .into(),
-        );
+        let mut cursor = Cursor::from_slice(
+            memory,
+            self.ptr_to_relocations as usize,
+            self.number_of_relocations as usize * 10,
+        )?;
         (0..self.number_of_relocations)
             .map(|_| {
                 Ok(CoffRelocation {
-                    virtual_address: cursor.read_u32(),
-                    symbol_table_index: cursor.read_u32(),
-                    r#type: TypeIndicatorX64::try_from(cursor.read_u16())?,
+                    virtual_address: cursor.read_u32()?,
+                    symbol_table_index: cursor.read_u32()?,
+                    r#type: RelocationType::from_machine(machine, cursor.read_u16()?)?,
                 })
             })
             .collect()
     }
 
     //  https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#coff-line-numbers-deprecated
-    pub fn coff_line_numbers<'a>(&self, memory: &'a Vec<u8>) -> Vec<LineNumber> {
-        let mut cursor = Cursor::new(
-            memory[self.ptr_to_linenumbers as usize
-                ..self.ptr_to_linenumbers as usize + (self.number_of_linenumbers as usize * 6)]
-                .into(),
-        );
+    pub fn coff_line_numbers(&self, memory: &[u8]) -> Result<Vec<LineNumber>, PeError> {
+        let mut cursor = Cursor::from_slice(
+            memory,
+            self.ptr_to_linenumbers as usize,
+            self.number_of_linenumbers as usize * 6,
+        )?;
         let mut line_nums = vec![];
         for _ in 0..self.number_of_linenumbers {
             line_nums.push(LineNumber {
-                r#type: cursor.read_u32(),
-                linenumber: cursor.read_u16(),
+                r#type: cursor.read_u32()?,
+                linenumber: cursor.read_u16()?,
             });
         }
-        line_nums
-    }
-
-    //  FIXME: i'm not done
-    //  https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#coff-symbol-table
-    pub fn coff_symbol_table<'a>(
-        &self,
-        memory: &'a Vec<u8>,
-        ptr_to_start: u32,
-    ) -> Vec<SymbolTableRecord> {
-        let mut cursor = Cursor::new(
-            memory[ptr_to_start as usize
-                ..ptr_to_start as usize + (self.number_of_relocations * 18) as usize]
-                .into(),
-        );
-
-        let mut records = vec![];
-        for _ in 0..self.number_of_relocations {
-            let result = SymbolTableRecord::Standard(StandardSymbolRecord {
-                name: SymbolName {
-                    short_name: cursor.read_u32(),
-                    zeroes: cursor.read_u16(),
-                    offset: cursor.read_u16(),
-                },
-                value: cursor.read_u32(),
-                section_number: SectionNumber::from(cursor.read_i16()),
-                r#type: SymbolType::from(cursor.read_u16()),
-                storage_class: StorageClass::from(cursor.read_u8()),
-                number_of_aux_symbols: cursor.read_u8(),
-            });
-            records.push(result);
-        }
-        records
+        Ok(line_nums)
     }
 }
 
@@ -200,7 +214,9 @@ pub enum SymbolTableRecord {
 /// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#coff-symbol-table
 #[derive(Debug, Clone)]
 pub struct StandardSymbolRecord {
-    pub name: SymbolName,
+    /// The name, long names are resolved through the string table
+    pub name: String,
+    pub raw_name: SymbolName,
     pub value: u32,
     pub section_number: SectionNumber,
     pub r#type: SymbolType,
@@ -210,10 +226,9 @@ pub struct StandardSymbolRecord {
 
 impl StandardSymbolRecord {
     pub fn is_function_definition(&self) -> bool {
-        let num: i16 = self.section_number.clone() as i16;
         self.storage_class == StorageClass::IMAGE_SYM_CLASS_EXTERNAL
             && self.r#type == SymbolType::FUNCTION
-            && num > 0
+            && matches!(self.section_number, SectionNumber::Index(_))
     }
 }
 
@@ -254,22 +269,32 @@ pub enum AuxiliarySymbolRecord {
         selection: u8,
         unused: [u8; 3],
     },
+    /// Any other record, e.g. the CLR token definitions
+    Other([u8; 18]),
 }
 
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#section-number-values
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum SectionNumber {
     IMAGE_SYM_UNDEFINED,
     IMAGE_SYM_ABSOLUTE,
     IMAGE_SYM_DEBUG,
+    /// One based index into the section table
+    Index(u16),
 }
 
-impl From<i16> for SectionNumber {
-    fn from(value: i16) -> Self {
+impl TryFrom<i16> for SectionNumber {
+    type Error = PeError;
+    fn try_from(value: i16) -> Result<Self, PeError> {
         match value {
-            0 => SectionNumber::IMAGE_SYM_UNDEFINED,
-            -1 => SectionNumber::IMAGE_SYM_ABSOLUTE,
-            -2 => SectionNumber::IMAGE_SYM_DEBUG,
-            _ => panic!("Invalid section number"),
+            0 => Ok(SectionNumber::IMAGE_SYM_UNDEFINED),
+            -1 => Ok(SectionNumber::IMAGE_SYM_ABSOLUTE),
+            -2 => Ok(SectionNumber::IMAGE_SYM_DEBUG),
+            1.. => Ok(SectionNumber::Index(value as u16)),
+            _ => Err(PeError::ParseError(format!(
+                "Invalid section number: {}",
+                value
+            ))),
         }
     }
 }
@@ -306,9 +331,10 @@ pub enum StorageClass {
     IMAGE_SYM_CLASS_CLR_TOKEN,
 }
 
-impl From<u8> for StorageClass {
-    fn from(value: u8) -> Self {
-        match value {
+impl TryFrom<u8> for StorageClass {
+    type Error = PeError;
+    fn try_from(value: u8) -> Result<Self, PeError> {
+        Ok(match value {
             0xFF => StorageClass::IMAGE_SYM_CLASS_END_OF_FUNCTION,
             0 => StorageClass::IMAGE_SYM_CLASS_NULL,
             1 => StorageClass::IMAGE_SYM_CLASS_AUTOMATIC,
@@ -336,16 +362,33 @@ impl From<u8> for StorageClass {
             104 => StorageClass::IMAGE_SYM_CLASS_SECTION,
             105 => StorageClass::IMAGE_SYM_CLASS_WEAK_EXTERNAL,
             107 => StorageClass::IMAGE_SYM_CLASS_CLR_TOKEN,
-            _ => panic!("Invalid storage class"),
-        }
+            _ => {
+                return Err(PeError::ParseError(format!(
+                    "Invalid storage class: {:#x?}",
+                    value
+                )))
+            }
+        })
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct SymbolName {
-    pub short_name: u32,
-    pub zeroes: u16,
-    pub offset: u16,
+/// The 8 byte name field, a null padded name or, when the first 4 bytes are zero,
+/// an offset into the string table
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SymbolName {
+    Short([u8; 8]),
+    Long { offset: u32 },
+}
+
+impl From<[u8; 8]> for SymbolName {
+    fn from(value: [u8; 8]) -> Self {
+        match value[..4] {
+            [0, 0, 0, 0] => SymbolName::Long {
+                offset: u32::from_le_bytes(value[4..].try_into().unwrap()),
+            },
+            _ => SymbolName::Short(value),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -370,9 +413,10 @@ pub enum SymbolType {
     FUNCTION,
 }
 
-impl From<u16> for SymbolType {
-    fn from(value: u16) -> Self {
-        match value {
+impl TryFrom<u16> for SymbolType {
+    type Error = PeError;
+    fn try_from(value: u16) -> Result<Self, PeError> {
+        Ok(match value {
             0 => SymbolType::IMAGE_SYM_TYPE_NULL,
             1 => SymbolType::IMAGE_SYM_TYPE_VOID,
             2 => SymbolType::IMAGE_SYM_TYPE_CHAR,
@@ -390,8 +434,13 @@ impl From<u16> for SymbolType {
             14 => SymbolType::IMAGE_SYM_TYPE_UINT,
             15 => SymbolType::IMAGE_SYM_TYPE_DWORD,
             0x20 => SymbolType::FUNCTION,
-            _ => panic!("Invalid symbol type"),
-        }
+            _ => {
+                return Err(PeError::ParseError(format!(
+                    "Invalid symbol type: {:#x?}",
+                    value
+                )))
+            }
+        })
     }
 }
 
@@ -407,13 +456,14 @@ pub struct LineNumber {
 pub struct CoffRelocation {
     pub virtual_address: u32,
     pub symbol_table_index: u32,
-    pub r#type: TypeIndicatorX64,
+    pub r#type: RelocationType,
 }
 
-//  TODO: support other archs
-/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#x64-processors
-#[derive(Debug, Clone)]
-pub enum TypeIndicatorX64 {
+/// The type of a COFF relocation, the same value means something else on every machine
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#type-indicators
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RelocationType {
+    //  https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#x64-processors
     IMAGE_REL_AMD64_ABSOLUTE,
     IMAGE_REL_AMD64_ADDR64,
     IMAGE_REL_AMD64_ADDR32,
@@ -431,35 +481,150 @@ pub enum TypeIndicatorX64 {
     IMAGE_REL_AMD64_SREL32,
     IMAGE_REL_AMD64_PAIR,
     IMAGE_REL_AMD64_SSPAN32,
+    //  https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#intel-386-processors
+    IMAGE_REL_I386_ABSOLUTE,
+    IMAGE_REL_I386_DIR16,
+    IMAGE_REL_I386_REL16,
+    IMAGE_REL_I386_DIR32,
+    IMAGE_REL_I386_DIR32NB,
+    IMAGE_REL_I386_SEG12,
+    IMAGE_REL_I386_SECTION,
+    IMAGE_REL_I386_SECREL,
+    IMAGE_REL_I386_TOKEN,
+    IMAGE_REL_I386_SECREL7,
+    IMAGE_REL_I386_REL32,
+    //  https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#arm-processors
+    IMAGE_REL_ARM_ABSOLUTE,
+    IMAGE_REL_ARM_ADDR32,
+    IMAGE_REL_ARM_ADDR32NB,
+    IMAGE_REL_ARM_BRANCH24,
+    IMAGE_REL_ARM_BRANCH11,
+    IMAGE_REL_ARM_REL32,
+    IMAGE_REL_ARM_SECTION,
+    IMAGE_REL_ARM_SECREL,
+    IMAGE_REL_ARM_MOV32,
+    IMAGE_REL_ARM_THUMB_MOV32,
+    IMAGE_REL_ARM_THUMB_BRANCH20,
+    IMAGE_REL_ARM_UNUSED,
+    IMAGE_REL_ARM_THUMB_BRANCH24,
+    IMAGE_REL_ARM_THUMB_BLX23,
+    IMAGE_REL_ARM_PAIR,
+    //  https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#arm64-processors
+    IMAGE_REL_ARM64_ABSOLUTE,
+    IMAGE_REL_ARM64_ADDR32,
+    IMAGE_REL_ARM64_ADDR32NB,
+    IMAGE_REL_ARM64_BRANCH26,
+    IMAGE_REL_ARM64_PAGEBASE_REL21,
+    IMAGE_REL_ARM64_REL21,
+    IMAGE_REL_ARM64_PAGEOFFSET_12A,
+    IMAGE_REL_ARM64_PAGEOFFSET_12L,
+    IMAGE_REL_ARM64_SECREL,
+    IMAGE_REL_ARM64_SECREL_LOW12A,
+    IMAGE_REL_ARM64_SECREL_HIGH12A,
+    IMAGE_REL_ARM64_SECREL_LOW12L,
+    IMAGE_REL_ARM64_TOKEN,
+    IMAGE_REL_ARM64_SECTION,
+    IMAGE_REL_ARM64_ADDR64,
+    IMAGE_REL_ARM64_BRANCH19,
+    IMAGE_REL_ARM64_BRANCH14,
+    IMAGE_REL_ARM64_REL32,
 }
 
-impl TryFrom<u16> for TypeIndicatorX64 {
-    type Error = PeError;
-
-    fn try_from(value: u16) -> Result<Self, PeError> {
-        match value {
-            0x0000 => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_ABSOLUTE),
-            0x0001 => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_ADDR64),
-            0x0002 => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_ADDR32),
-            0x0003 => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_ADDR32NB),
-            0x0004 => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_REL32),
-            0x0005 => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_REL32_1),
-            0x0006 => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_REL32_2),
-            0x0007 => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_REL32_3),
-            0x0008 => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_REL32_4),
-            0x0009 => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_REL32_5),
-            0x000A => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_SECTION),
-            0x000B => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_SECREL),
-            0x000C => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_SECREL7),
-            0x000D => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_TOKEN),
-            0x000E => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_SREL32),
-            0x000F => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_PAIR),
-            0x0010 => Ok(TypeIndicatorX64::IMAGE_REL_AMD64_SSPAN32),
-            _ => Err(PeError::ParseError(format!(
-                "Invalid type indicator: {:#x?}",
-                value
-            ))),
-        }
+impl RelocationType {
+    pub fn from_machine(machine: &Machine, value: u16) -> Result<Self, PeError> {
+        use RelocationType::*;
+        let r#type = match machine {
+            Machine::IMAGE_FILE_MACHINE_AMD64 => match value {
+                0x0000 => Some(IMAGE_REL_AMD64_ABSOLUTE),
+                0x0001 => Some(IMAGE_REL_AMD64_ADDR64),
+                0x0002 => Some(IMAGE_REL_AMD64_ADDR32),
+                0x0003 => Some(IMAGE_REL_AMD64_ADDR32NB),
+                0x0004 => Some(IMAGE_REL_AMD64_REL32),
+                0x0005 => Some(IMAGE_REL_AMD64_REL32_1),
+                0x0006 => Some(IMAGE_REL_AMD64_REL32_2),
+                0x0007 => Some(IMAGE_REL_AMD64_REL32_3),
+                0x0008 => Some(IMAGE_REL_AMD64_REL32_4),
+                0x0009 => Some(IMAGE_REL_AMD64_REL32_5),
+                0x000A => Some(IMAGE_REL_AMD64_SECTION),
+                0x000B => Some(IMAGE_REL_AMD64_SECREL),
+                0x000C => Some(IMAGE_REL_AMD64_SECREL7),
+                0x000D => Some(IMAGE_REL_AMD64_TOKEN),
+                0x000E => Some(IMAGE_REL_AMD64_SREL32),
+                0x000F => Some(IMAGE_REL_AMD64_PAIR),
+                0x0010 => Some(IMAGE_REL_AMD64_SSPAN32),
+                _ => None,
+            },
+            Machine::IMAGE_FILE_MACHINE_I386 => match value {
+                0x0000 => Some(IMAGE_REL_I386_ABSOLUTE),
+                0x0001 => Some(IMAGE_REL_I386_DIR16),
+                0x0002 => Some(IMAGE_REL_I386_REL16),
+                0x0006 => Some(IMAGE_REL_I386_DIR32),
+                0x0007 => Some(IMAGE_REL_I386_DIR32NB),
+                0x0009 => Some(IMAGE_REL_I386_SEG12),
+                0x000A => Some(IMAGE_REL_I386_SECTION),
+                0x000B => Some(IMAGE_REL_I386_SECREL),
+                0x000C => Some(IMAGE_REL_I386_TOKEN),
+                0x000D => Some(IMAGE_REL_I386_SECREL7),
+                0x0014 => Some(IMAGE_REL_I386_REL32),
+                _ => None,
+            },
+            Machine::IMAGE_FILE_MACHINE_ARM
+            | Machine::IMAGE_FILE_MACHINE_ARMNT
+            | Machine::IMAGE_FILE_MACHINE_THUMB => match value {
+                0x0000 => Some(IMAGE_REL_ARM_ABSOLUTE),
+                0x0001 => Some(IMAGE_REL_ARM_ADDR32),
+                0x0002 => Some(IMAGE_REL_ARM_ADDR32NB),
+                0x0003 => Some(IMAGE_REL_ARM_BRANCH24),
+                0x0004 => Some(IMAGE_REL_ARM_BRANCH11),
+                0x000A => Some(IMAGE_REL_ARM_REL32),
+                0x000E => Some(IMAGE_REL_ARM_SECTION),
+                0x000F => Some(IMAGE_REL_ARM_SECREL),
+                0x0010 => Some(IMAGE_REL_ARM_MOV32),
+                0x0011 => Some(IMAGE_REL_ARM_THUMB_MOV32),
+                0x0012 => Some(IMAGE_REL_ARM_THUMB_BRANCH20),
+                0x0013 => Some(IMAGE_REL_ARM_UNUSED),
+                0x0014 => Some(IMAGE_REL_ARM_THUMB_BRANCH24),
+                0x0015 => Some(IMAGE_REL_ARM_THUMB_BLX23),
+                0x0016 => Some(IMAGE_REL_ARM_PAIR),
+                _ => None,
+            },
+            //  ARM64EC objects hold ARM64 code
+            Machine::IMAGE_FILE_MACHINE_ARM64 | Machine::IMAGE_FILE_MACHINE_ARM64EC => {
+                match value {
+                    0x0000 => Some(IMAGE_REL_ARM64_ABSOLUTE),
+                    0x0001 => Some(IMAGE_REL_ARM64_ADDR32),
+                    0x0002 => Some(IMAGE_REL_ARM64_ADDR32NB),
+                    0x0003 => Some(IMAGE_REL_ARM64_BRANCH26),
+                    0x0004 => Some(IMAGE_REL_ARM64_PAGEBASE_REL21),
+                    0x0005 => Some(IMAGE_REL_ARM64_REL21),
+                    0x0006 => Some(IMAGE_REL_ARM64_PAGEOFFSET_12A),
+                    0x0007 => Some(IMAGE_REL_ARM64_PAGEOFFSET_12L),
+                    0x0008 => Some(IMAGE_REL_ARM64_SECREL),
+                    0x0009 => Some(IMAGE_REL_ARM64_SECREL_LOW12A),
+                    0x000A => Some(IMAGE_REL_ARM64_SECREL_HIGH12A),
+                    0x000B => Some(IMAGE_REL_ARM64_SECREL_LOW12L),
+                    0x000C => Some(IMAGE_REL_ARM64_TOKEN),
+                    0x000D => Some(IMAGE_REL_ARM64_SECTION),
+                    0x000E => Some(IMAGE_REL_ARM64_ADDR64),
+                    0x000F => Some(IMAGE_REL_ARM64_BRANCH19),
+                    0x0010 => Some(IMAGE_REL_ARM64_BRANCH14),
+                    0x0011 => Some(IMAGE_REL_ARM64_REL32),
+                    _ => None,
+                }
+            }
+            _ => {
+                return Err(PeError::ParseError(format!(
+                    "Relocations of {:?} objects aren't supported",
+                    machine
+                )))
+            }
+        };
+        r#type.ok_or_else(|| {
+            PeError::ParseError(format!(
+                "Invalid {:?} relocation type: {:#x?}",
+                machine, value
+            ))
+        })
     }
 }
 
@@ -518,3 +683,18 @@ impl FromStr for SectionFlags {
         bitflags::parser::from_str(flags)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::pe::PortableExecutable;
+
+    #[test]
+    fn finds_sections_by_exact_name() {
+        //  `.textbss` comes first and used to shadow `.text`
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        let text = pe.section_table.get_section_header(".text").unwrap();
+        assert_eq!(text.name(), ".text");
+        assert!(text.virtual_address > pe.section_table.section_headers[0].virtual_address);
+        assert!(pe.section_table.get_section_header(".tex").is_none());
+    }
+}
@@ -0,0 +1,252 @@
+use super::{
+    cursor::{cstr_at, u32_at, Cursor},
+    section_table::{
+        AuxiliarySymbolRecord, SectionNumber, StandardSymbolRecord, StorageClass, SymbolName,
+        SymbolTableRecord, SymbolType,
+    },
+    PeError,
+};
+
+const SYMBOL_SIZE: usize = 18;
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#coff-string-table
+pub fn get_string_table(
+    bytes: &[u8],
+    ptr_to_symbol_table: u32,
+    number_of_symbols: u32,
+) -> Result<StringTable, PeError> {
+    //  the table follows the symbols and starts with its own size
+    let offset = ptr_to_symbol_table as usize + number_of_symbols as usize * SYMBOL_SIZE;
+    if offset == bytes.len() {
+        return Ok(StringTable::default());
+    }
+    let size = u32_at(bytes, offset)? as usize;
+    if size < 4 {
+        return Ok(StringTable::default());
+    }
+    let mut cursor = Cursor::from_slice(bytes, offset, size)?;
+    Ok(StringTable {
+        data: cursor.read(size)?,
+    })
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#coff-symbol-table
+pub fn get_symbol_table(
+    bytes: &[u8],
+    ptr_to_symbol_table: u32,
+    number_of_symbols: u32,
+) -> Result<SymbolTable, PeError> {
+    let string_table = get_string_table(bytes, ptr_to_symbol_table, number_of_symbols)?;
+    let mut cursor = Cursor::from_slice(
+        bytes,
+        ptr_to_symbol_table as usize,
+        number_of_symbols as usize * SYMBOL_SIZE,
+    )?;
+
+    let mut records = Vec::with_capacity(number_of_symbols as usize);
+    while records.len() < number_of_symbols as usize {
+        let raw_name = SymbolName::from(<[u8; 8]>::try_from(cursor.read_slice(8)?).unwrap());
+        let symbol = StandardSymbolRecord {
+            name: string_table.symbol_name(&raw_name)?,
+            raw_name,
+            value: cursor.read_u32()?,
+            section_number: SectionNumber::try_from(cursor.read_i16()?)?,
+            r#type: SymbolType::try_from(cursor.read_u16()?)?,
+            storage_class: StorageClass::try_from(cursor.read_u8()?)?,
+            number_of_aux_symbols: cursor.read_u8()?,
+        };
+        //  the auxiliary records are counted in NumberOfSymbols and take up a slot each
+        let aux_records = (0..symbol.number_of_aux_symbols)
+            .map(|_| parse_aux_record(&symbol, cursor.read_slice(SYMBOL_SIZE)?))
+            .collect::<Result<Vec<_>, PeError>>()?;
+        records.push(SymbolTableRecord::Standard(symbol));
+        records.extend(aux_records.into_iter().map(SymbolTableRecord::Auxiliary));
+    }
+    Ok(SymbolTable {
+        records,
+        string_table,
+    })
+}
+
+/// The format of an auxiliary record depends on the symbol it follows
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#auxiliary-symbol-records
+fn parse_aux_record(
+    symbol: &StandardSymbolRecord,
+    data: &[u8],
+) -> Result<AuxiliarySymbolRecord, PeError> {
+    use StorageClass::*;
+    let mut cursor = Cursor::new(data);
+    let record = match symbol.storage_class {
+        IMAGE_SYM_CLASS_EXTERNAL if symbol.is_function_definition() => {
+            AuxiliarySymbolRecord::FnDefinitions {
+                tag_index: cursor.read_u32()?,
+                total_size: cursor.read_u32()?,
+                ptr_to_linenumber: cursor.read_u32()?,
+                ptr_to_next_fn: cursor.read_u32()?,
+                unused: cursor.read_u16()?,
+            }
+        }
+        IMAGE_SYM_CLASS_FUNCTION => AuxiliarySymbolRecord::BfAndEf {
+            unused: cursor.read_u32()?,
+            linenumber: cursor.read_u16()?,
+            unused2: cursor.read_slice(6)?.try_into().unwrap(),
+            ptr_to_next_fn: cursor.read_u32()?,
+            unused3: cursor.read_u16()?,
+        },
+        IMAGE_SYM_CLASS_WEAK_EXTERNAL => weak_external(&mut cursor)?,
+        //  the older form of a weak external
+        IMAGE_SYM_CLASS_EXTERNAL
+            if symbol.section_number == SectionNumber::IMAGE_SYM_UNDEFINED && symbol.value == 0 =>
+        {
+            weak_external(&mut cursor)?
+        }
+        IMAGE_SYM_CLASS_FILE => AuxiliarySymbolRecord::Files {
+            file_name: data.try_into().unwrap(),
+        },
+        IMAGE_SYM_CLASS_STATIC if matches!(symbol.section_number, SectionNumber::Index(_)) => {
+            AuxiliarySymbolRecord::SectionDefinitions {
+                length: cursor.read_u32()?,
+                number_of_relocations: cursor.read_u16()?,
+                number_of_linenumbers: cursor.read_u16()?,
+                checksum: cursor.read_u32()?,
+                number: cursor.read_u16()?,
+                selection: cursor.read_u8()?,
+                unused: cursor.read_slice(3)?.try_into().unwrap(),
+            }
+        }
+        _ => AuxiliarySymbolRecord::Other(data.try_into().unwrap()),
+    };
+    Ok(record)
+}
+
+fn weak_external(cursor: &mut Cursor) -> Result<AuxiliarySymbolRecord, PeError> {
+    Ok(AuxiliarySymbolRecord::WeakExternals {
+        tag_index: cursor.read_u32()?,
+        characteristics: cursor.read_u32()?,
+        unused: cursor.read_slice(10)?.try_into().unwrap(),
+    })
+}
+
+/// The symbol table as laid out in the file, the auxiliary records follow their symbol
+/// so indices match the ones relocations use
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    pub records: Vec<SymbolTableRecord>,
+    pub string_table: StringTable,
+}
+
+impl SymbolTable {
+    /// Every standard record with its index
+    pub fn symbols(&self) -> impl Iterator<Item = (u32, &StandardSymbolRecord)> {
+        self.records
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, record)| match record {
+                SymbolTableRecord::Standard(symbol) => Some((idx as u32, symbol)),
+                SymbolTableRecord::Auxiliary(_) => None,
+            })
+    }
+
+    /// The standard record at `index`, `None` for auxiliary records
+    pub fn get(&self, index: u32) -> Option<&StandardSymbolRecord> {
+        match self.records.get(index as usize)? {
+            SymbolTableRecord::Standard(symbol) => Some(symbol),
+            SymbolTableRecord::Auxiliary(_) => None,
+        }
+    }
+
+    /// The auxiliary records of the symbol at `index`
+    pub fn aux_records(&self, index: u32) -> impl Iterator<Item = &AuxiliarySymbolRecord> {
+        self.records
+            .iter()
+            .skip(index as usize + 1)
+            .map_while(|record| match record {
+                SymbolTableRecord::Auxiliary(aux) => Some(aux),
+                SymbolTableRecord::Standard(_) => None,
+            })
+    }
+
+    pub fn find(&self, name: &str) -> Option<(u32, &StandardSymbolRecord)> {
+        self.symbols().find(|(_, symbol)| symbol.name == name)
+    }
+}
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#coff-string-table
+#[derive(Debug, Clone, Default)]
+pub struct StringTable {
+    /// The whole table, offsets count the 4 byte size at the start
+    pub data: Vec<u8>,
+}
+
+impl StringTable {
+    pub fn get(&self, offset: u32) -> Result<String, PeError> {
+        if offset < 4 {
+            return Err(PeError::InvalidString {
+                offset: offset as usize,
+            });
+        }
+        Ok(String::from_utf8_lossy(cstr_at(&self.data, offset as usize)?).to_string())
+    }
+
+    pub fn symbol_name(&self, name: &SymbolName) -> Result<String, PeError> {
+        match name {
+            SymbolName::Short(name) => {
+                let len = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+                Ok(String::from_utf8_lossy(&name[..len]).to_string())
+            }
+            SymbolName::Long { offset } => self.get(*offset),
+        }
+    }
+
+    /// Section names longer than 8 bytes are stored as "/" followed by the decimal offset
+    /// of the full name, objects only
+    pub fn section_name(&self, name: &str) -> Result<String, PeError> {
+        let name = name.trim_end_matches('\0');
+        match name.strip_prefix('/').map(str::parse::<u32>) {
+            Some(Ok(offset)) => self.get(offset),
+            _ => Ok(name.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pe::{
+        section_table::{AuxiliarySymbolRecord, SectionNumber, SymbolName},
+        PortableExecutable,
+    };
+
+    #[test]
+    fn reads_mingw_symbols() {
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        let symbols = pe.symbol_table().unwrap();
+        //  NumberOfSymbols counts the auxiliary records
+        assert_eq!(symbols.records.len(), 1015);
+        assert_eq!(symbols.symbols().count(), 750);
+
+        let (idx, main) = symbols.find("main").unwrap();
+        assert_eq!(main.value, 0x450);
+        assert_eq!(main.section_number, SectionNumber::Index(1));
+        assert!(matches!(
+            symbols.aux_records(idx).collect::<Vec<_>>()[..],
+            [AuxiliarySymbolRecord::FnDefinitions { .. }]
+        ));
+
+        let (_, handler) = symbols.find("__mingw_invalidParameterHandler").unwrap();
+        assert!(matches!(handler.raw_name, SymbolName::Long { .. }));
+
+        let names = pe
+            .section_table
+            .section_headers
+            .iter()
+            .map(|section| pe.section_name(section))
+            .collect::<Vec<_>>();
+        assert_eq!(names[0], ".text");
+        assert_eq!(names[10], ".debug_aranges");
+        assert_eq!(names[16], ".debug_line_str");
+
+        //  MSVC strips them
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        assert!(pe.symbol_table().is_err());
+    }
+}
@@ -0,0 +1,39 @@
+//! Helpers to hand-assemble tables for tests the sample binaries don't cover
+
+use super::section_table::{SectionFlags, SectionHeader, SectionTable};
+
+pub fn put_u16(bytes: &mut [u8], offset: usize, value: u16) {
+    bytes[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+pub fn put_u32(bytes: &mut [u8], offset: usize, value: u32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+pub fn put_bytes(bytes: &mut [u8], offset: usize, value: impl AsRef<[u8]>) {
+    let value = value.as_ref();
+    bytes[offset..offset + value.len()].copy_from_slice(value);
+}
+
+/// An initialized data section whose file data is as large as its virtual size
+pub fn section(name: &str, virtual_address: u32, ptr_to_raw_data: u32, size: u32) -> SectionHeader {
+    SectionHeader {
+        name: name.to_string(),
+        virtual_size: size,
+        virtual_address,
+        size_of_raw_data: size,
+        ptr_to_raw_data,
+        ptr_to_relocations: 0,
+        ptr_to_linenumbers: 0,
+        number_of_relocations: 0,
+        number_of_linenumbers: 0,
+        characteristics: SectionFlags::IMAGE_SCN_CNT_INITIALIZED_DATA,
+        raw_data: vec![],
+    }
+}
+
+pub fn section_table(section_headers: impl Into<Vec<SectionHeader>>) -> SectionTable {
+    SectionTable {
+        section_headers: section_headers.into(),
+    }
+}
@@ -0,0 +1,106 @@
+use crate::util::{u32_from_bytes, u64_from_bytes};
+
+use super::{
+    cursor::Cursor,
+    import_table::rva2foa,
+    optional_header::{ExecutableKind, ImageDataDirectory},
+    section_table::SectionTable,
+    PeError,
+};
+
+/// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#the-tls-section
+pub fn get_tls_directory(
+    section_table: &SectionTable,
+    bytes: &[u8],
+    exec_kind: &ExecutableKind,
+    image_base: u64,
+    tls_table_dir: ImageDataDirectory,
+) -> Result<TlsDirectory, PeError> {
+    if tls_table_dir.virtual_address == 0 || tls_table_dir.size == 0 {
+        return Err(PeError::MissingTable(
+            "The executable doesn't have a TLS directory".to_string(),
+        ));
+    }
+    let tls_foa = rva2foa(tls_table_dir.virtual_address, section_table)? as usize;
+    let address_size = match exec_kind {
+        ExecutableKind::PE32 => 4,
+        ExecutableKind::PE32_PLUS => 8,
+    };
+    let mut cursor = Cursor::from_slice(bytes, tls_foa, address_size * 4 + 8)?;
+    let read_address = |cursor: &mut Cursor| match exec_kind {
+        ExecutableKind::PE32 => cursor.read_u32().map(|address| address as u64),
+        ExecutableKind::PE32_PLUS => cursor.read_u64(),
+    };
+    let mut directory = TlsDirectory {
+        start_address_of_raw_data: read_address(&mut cursor)?,
+        end_address_of_raw_data: read_address(&mut cursor)?,
+        address_of_index: read_address(&mut cursor)?,
+        address_of_callbacks: read_address(&mut cursor)?,
+        size_of_zero_fill: cursor.read_u32()?,
+        characteristics: cursor.read_u32()?,
+        callbacks: vec![],
+    };
+
+    //  the callback array is a null terminated list of VAs
+    if directory.address_of_callbacks != 0 {
+        let callbacks_rva = directory.address_of_callbacks.wrapping_sub(image_base) as u32;
+        let mut callback_foa = rva2foa(callbacks_rva, section_table)? as usize;
+        while callback_foa + address_size <= bytes.len() {
+            let callback = match exec_kind {
+                ExecutableKind::PE32 => u32_from_bytes(&bytes[callback_foa..]) as u64,
+                ExecutableKind::PE32_PLUS => u64_from_bytes(&bytes[callback_foa..]),
+            };
+            if callback == 0 {
+                break;
+            }
+            callback_foa += address_size;
+            directory
+                .callbacks
+                .push(callback.wrapping_sub(image_base) as u32);
+        }
+    }
+    Ok(directory)
+}
+
+/// Aka IMAGE_TLS_DIRECTORY32/IMAGE_TLS_DIRECTORY64, addresses are VAs widened to u64
+#[derive(Debug, Clone)]
+pub struct TlsDirectory {
+    pub start_address_of_raw_data: u64,
+    pub end_address_of_raw_data: u64,
+    pub address_of_index: u64,
+    pub address_of_callbacks: u64,
+    pub size_of_zero_fill: u32,
+    pub characteristics: u32,
+
+    ///  not in MS docs, RVAs of the TLS callbacks
+    pub callbacks: Vec<u32>,
+}
+
+impl TlsDirectory {
+    /// VA range of the TLS template data
+    pub fn raw_data_range(&self) -> std::ops::Range<u64> {
+        self.start_address_of_raw_data..self.end_address_of_raw_data
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pe::{PeError, PortableExecutable};
+
+    #[test]
+    fn parses_tls_directory() {
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+        let tls = pe.tls_directory().unwrap();
+        assert_eq!(tls.raw_data_range(), 0x14000a000..0x14000a008);
+        assert_eq!(tls.size_of_zero_fill, 0);
+        assert!(!tls.callbacks.is_empty());
+        for callback in &tls.callbacks {
+            let text = pe.section_table.get_section_header(".text").unwrap();
+            assert!(*callback >= text.virtual_address);
+            assert!(*callback < text.virtual_address + text.virtual_size);
+        }
+
+        let pe = PortableExecutable::from_file("sample_executable_x86.exe").unwrap();
+        assert!(matches!(pe.tls_directory(), Err(PeError::MissingTable(_))));
+    }
+}
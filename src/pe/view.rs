@@ -0,0 +1,142 @@
+use super::{
+    cursor::{slice_at, Cursor},
+    dos_header::{parse_dos_header, DosHeader},
+    export_table::{get_export_table, ExportTable},
+    file_header::parse_file_header,
+    import_table::{get_import_table, ImportTable},
+    optional_header::{parse_opt_header, ExecutableKind, ImageDataDirectory, ImageDirectoryEntry},
+    section_table::{parse_section_headers, SectionHeader, SectionTable},
+    NtHeaders, PeError,
+};
+
+/// A PE borrowed from a byte slice, e.g. a memory mapped file.
+/// Only the headers are parsed up front, everything else is read on demand without copying the file
+#[derive(Clone)]
+pub struct PeView<'a> {
+    bytes: &'a [u8],
+    pub dos_header: DosHeader,
+    pub nt_headers: NtHeaders,
+    /// The headers' `raw_data` is left empty, use [`PeView::section_data`]
+    pub section_table: SectionTable,
+    pub executable_type: ExecutableKind,
+}
+
+impl<'a> PeView<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, PeError> {
+        let mut cursor = Cursor::new(bytes);
+        let dos_header = parse_dos_header(&mut cursor)?;
+
+        cursor.position = dos_header.e_lfanew as usize;
+        let pe_signature = cursor.read_u16()?;
+        if pe_signature != 0x4550 || cursor.read_u16()? != 0 {
+            return Err(PeError::ParseError(format!(
+                "Could not find the PE signature at {:#x}",
+                dos_header.e_lfanew
+            )));
+        }
+
+        let file_header = parse_file_header(&mut cursor)?;
+        let opt_header = parse_opt_header(&mut cursor)?;
+        let executable_type = opt_header.std_fields.magic.clone();
+        //  the section table follows the optional header as sized by the file header
+        cursor.position =
+            dos_header.e_lfanew as usize + 4 + 20 + file_header.size_of_optional_header as usize;
+        let section_table = parse_section_headers(&mut cursor, file_header.number_of_sections)?;
+
+        Ok(PeView {
+            bytes,
+            dos_header,
+            nt_headers: NtHeaders {
+                pe_signature,
+                file_header,
+                opt_header,
+            },
+            section_table,
+            executable_type,
+        })
+    }
+
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// The section's file data, borrowed from the input
+    pub fn section_data(&self, section: &SectionHeader) -> Result<&'a [u8], PeError> {
+        slice_at(
+            self.bytes,
+            section.ptr_to_raw_data as usize,
+            section.size_of_raw_data as usize,
+        )
+    }
+
+    pub fn sections(&self) -> impl Iterator<Item = (&SectionHeader, Result<&'a [u8], PeError>)> {
+        self.section_table
+            .section_headers
+            .iter()
+            .map(|section| (section, self.section_data(section)))
+    }
+
+    pub fn get_import_table(&self) -> Result<ImportTable, PeError> {
+        get_import_table(
+            &self.section_table,
+            self.bytes,
+            &self.executable_type,
+            self.get_image_directory(ImageDirectoryEntry::IMPORT),
+        )
+    }
+
+    pub fn get_export_table(&self) -> Result<ExportTable, PeError> {
+        get_export_table(
+            &self.section_table,
+            self.bytes,
+            self.get_image_directory(ImageDirectoryEntry::EXPORT),
+        )
+    }
+
+    pub fn get_image_directory(&self, entry: ImageDirectoryEntry) -> ImageDataDirectory {
+        self.nt_headers.opt_header.data_directories[entry as usize].clone()
+    }
+}
+
+/// Maps the file read only, parse the result with [`PeView::parse`]
+///
+/// # Safety
+/// The mapping is undefined behavior if the file is modified or truncated while it's alive,
+/// see [`memmap2::Mmap::map`]
+pub unsafe fn map_file(path: impl AsRef<std::path::Path>) -> Result<memmap2::Mmap, PeError> {
+    let file = std::fs::File::open(path)?;
+    Ok(memmap2::Mmap::map(&file)?)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pe::PortableExecutable;
+
+    use super::{map_file, PeView};
+
+    #[test]
+    fn matches_the_owned_parser() {
+        let mmap = unsafe { map_file("sample_executable.exe") }.unwrap();
+        let view = PeView::parse(&mmap).unwrap();
+        let pe = PortableExecutable::from_file("sample_executable.exe").unwrap();
+
+        assert_eq!(
+            view.nt_headers.file_header.number_of_sections,
+            pe.nt_headers.file_header.number_of_sections
+        );
+        for ((header, data), owned) in view.sections().zip(&pe.section_table.section_headers) {
+            assert_eq!(header.name, owned.name);
+            assert!(header.raw_data.is_empty());
+            assert_eq!(data.unwrap(), owned.raw_data.as_slice());
+        }
+
+        let imports = view.get_import_table().unwrap();
+        let owned_imports = pe.get_import_table().unwrap();
+        assert_eq!(
+            imports.image_descriptors.len(),
+            owned_imports.image_descriptors.len()
+        );
+        assert_eq!(imports.image_descriptors[0].name, "KERNEL32.dll");
+        assert!(view.get_export_table().is_err());
+    }
+}
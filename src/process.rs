@@ -111,11 +111,7 @@ impl Process {
     ) -> Result<[u8; 260], WIN32_ERROR> {
         let mut lpfilename = [0u8; 260];
         unsafe {
-            let ok = GetModuleFileNameExA(
-                handle,
-                module.unwrap_or(HMODULE::default()),
-                &mut lpfilename,
-            );
+            let ok = GetModuleFileNameExA(handle, module.unwrap_or_default(), &mut lpfilename);
             match ok != 0 {
                 true => Ok(lpfilename),
                 false => Err(GetLastError()),
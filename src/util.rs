@@ -2,7 +2,7 @@ use crate::prelude::CHAR;
 
 pub fn wchar_arr_to_string(arr: &[CHAR]) -> String {
     let mut result = String::new();
-    for c in arr.into_iter() {
+    for c in arr.iter() {
         if *c == 0 {
             break;
         }
@@ -12,23 +12,19 @@ pub fn wchar_arr_to_string(arr: &[CHAR]) -> String {
 }
 
 pub fn u32_from_bytes(le_bytes: &[u8]) -> u32 {
-    let result = u32::from_le_bytes(le_bytes[0..4].try_into().unwrap());
-    result
+    u32::from_le_bytes(le_bytes[0..4].try_into().unwrap())
 }
 
 pub fn u16_from_bytes(le_bytes: &[u8]) -> u16 {
-    let result = u16::from_le_bytes(le_bytes[0..2].try_into().unwrap());
-    result
+    u16::from_le_bytes(le_bytes[0..2].try_into().unwrap())
 }
 
 pub fn i16_from_bytes(le_bytes: &[u8]) -> i16 {
-    let result = i16::from_le_bytes(le_bytes[0..2].try_into().unwrap());
-    result
+    i16::from_le_bytes(le_bytes[0..2].try_into().unwrap())
 }
 
 pub fn u64_from_bytes(le_bytes: &[u8]) -> u64 {
-    let result = u64::from_le_bytes(le_bytes[0..8].try_into().unwrap());
-    result
+    u64::from_le_bytes(le_bytes[0..8].try_into().unwrap())
 }
 
 pub fn read_u8_until_null(start: usize, data: &[u8]) -> &[u8] {
@@ -46,3 +42,7 @@ pub fn get_msb_u64(num: u64) -> u64 {
 pub fn get_msb_u32(num: u32) -> u32 {
     (num >> 31) & 1
 }
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
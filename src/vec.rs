@@ -18,12 +18,7 @@ impl Vec3 {
         while self.y > 180. {
             self.y -= 360.;
         }
-        if self.x > 89. {
-            self.x = 89.;
-        }
-        if self.x < -89. {
-            self.x = -89.;
-        }
+        self.x = self.x.clamp(-89., 89.);
         self.z = 0.;
     }
 